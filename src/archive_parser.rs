@@ -1,7 +1,7 @@
 use std::{
     fs::File,
     io::{Cursor, Read},
-    path::PathBuf,
+    path::{Path, PathBuf},
     str::from_utf8_unchecked,
 };
 
@@ -68,19 +68,27 @@ impl ArchiveEntry {
 
 impl Parser {
     const SIGNATURE: &str = "!<arch>\n";
+    const ELF_MAGIC: &[u8] = b"\x7fELF";
     pub fn new(libraries: Option<Vec<PathBuf>>) -> Self {
         Self { libraries }
     }
 
-    pub fn parse(&self, archive: String) -> Vec<InputElf> {
+    /// Search the library paths for `-l<archive>`, preferring the shared
+    /// library in each directory unless we are linking statically.
+    pub fn parse(&self, archive: String, is_static: bool, as_needed: bool) -> Vec<InputElf> {
         if let Some(libraries) = &self.libraries {
             for path in libraries {
-                let archive = format!("lib{}.a", archive);
-                let mut path = path.clone();
-                path.push(archive);
-                if let Ok(file) = File::open(&path) {
-                    dbg!("find archive", path);
-                    return Self::parse_inner(file);
+                let mut candidates = vec![];
+                if !is_static {
+                    candidates.push(format!("lib{}.so", archive));
+                }
+                candidates.push(format!("lib{}.a", archive));
+                for candidate in candidates {
+                    let mut path = path.clone();
+                    path.push(candidate);
+                    if path.is_file() {
+                        return self.open(path, is_static, as_needed);
+                    }
                 }
             }
             panic!("cannot find archive {archive}");
@@ -89,9 +97,103 @@ impl Parser {
         }
     }
 
-    fn parse_inner(mut file: File) -> Vec<InputElf> {
+    /// Open an input file given on the command line (or by a linker script),
+    /// which can be a relocatable object, a shared library, an archive, or
+    /// a linker script.
+    pub fn open(&self, path: PathBuf, is_static: bool, as_needed: bool) -> Vec<InputElf> {
+        let mut file = File::open(&path).unwrap_or_else(|_| panic!("cannot open file {:?}", &path));
         let mut contents = vec![];
         file.read_to_end(&mut contents).unwrap();
+
+        if contents.starts_with(Self::SIGNATURE.as_bytes()) {
+            Self::parse_inner(contents)
+        } else if contents.starts_with(Self::ELF_MAGIC) {
+            let name = path.file_name().unwrap().to_str().unwrap().to_string();
+            let mut elf = InputElf::new_from_buf(&contents, name);
+            // a shared library only gets into DT_NEEDED when it's alive,
+            // under --as-needed that happens once a live object references it
+            elf.is_alive = !(elf.is_dso && as_needed);
+            vec![elf]
+        } else {
+            let script = String::from_utf8_lossy(&contents).to_string();
+            let dir = path.parent().map(PathBuf::from).unwrap_or_default();
+            self.parse_script(&script, &dir, is_static, as_needed)
+        }
+    }
+
+    /// Handle the linker scripts that are installed in place of shared
+    /// libraries (e.g. libc.so), only `GROUP`, `INPUT` and `AS_NEEDED`
+    /// are supported. `dir` is the directory of the script.
+    fn parse_script(
+        &self,
+        script: &str,
+        dir: &Path,
+        is_static: bool,
+        as_needed: bool,
+    ) -> Vec<InputElf> {
+        let mut text = String::new();
+        let mut rest = script;
+        while let Some(start) = rest.find("/*") {
+            text.push_str(&rest[..start]);
+            rest = match rest[start..].find("*/") {
+                Some(end) => &rest[start + end + 2..],
+                None => "",
+            };
+        }
+        text.push_str(rest);
+        let text = text.replace('(', " ( ").replace(')', " ) ");
+        let tokens = text.split_whitespace().collect::<Vec<_>>();
+
+        let mut elfs = vec![];
+        // each entry tells whether the enclosing command is AS_NEEDED,
+        // and whether its arguments are input files at all
+        let mut stack: Vec<(bool, bool)> = vec![];
+        let mut command = "";
+        for token in tokens {
+            match token {
+                "(" => {
+                    let (outer_as_needed, _) = stack.last().cloned().unwrap_or((as_needed, true));
+                    let is_input = matches!(command, "GROUP" | "INPUT" | "AS_NEEDED");
+                    stack.push((outer_as_needed || command == "AS_NEEDED", is_input));
+                }
+                ")" => {
+                    stack.pop();
+                }
+                _ => match stack.last() {
+                    Some(&(as_needed, true)) if token != "AS_NEEDED" => {
+                        if let Some(lib) = token.strip_prefix("-l") {
+                            elfs.extend(self.parse(lib.to_string(), is_static, as_needed));
+                        } else {
+                            let path = self.find_script_input(token, dir);
+                            elfs.extend(self.open(path, is_static, as_needed));
+                        }
+                    }
+                    _ => {}
+                },
+            }
+            command = token;
+        }
+        elfs
+    }
+
+    /// A file named by a linker script is opened as is if it exists, else
+    /// searched in the directory of the script, then in the library paths.
+    fn find_script_input(&self, name: &str, dir: &Path) -> PathBuf {
+        let path = PathBuf::from(name);
+        if path.is_absolute() || path.is_file() {
+            return path;
+        }
+        let dirs = self.libraries.iter().flatten().map(PathBuf::as_path);
+        for dir in std::iter::once(dir).chain(dirs) {
+            let path = dir.join(name);
+            if path.is_file() {
+                return path;
+            }
+        }
+        panic!("cannot find {name} in the library paths");
+    }
+
+    fn parse_inner(contents: Vec<u8>) -> Vec<InputElf> {
         let total = contents.len() as u64;
         let mut cursor = Cursor::new(contents);
         let mut string_table = None;
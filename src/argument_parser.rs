@@ -1,8 +1,10 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{ArgAction, ArgMatches, Parser};
 
-#[derive(Parser, Debug)]
+use crate::utils::glob::glob_match;
+
+#[derive(Parser, Debug, Clone)]
 pub struct Args {
     pub objects: Vec<PathBuf>,
 
@@ -19,11 +21,11 @@ pub struct Args {
     #[arg(short = 'o', long, default_value = "a.out")]
     pub output: String,
 
-    // the -plugin option cannot be parsed, because short option
-    // only accept char
-    // but the plugin option is quite useless in linking, so
+    // the -plugin option cannot be parsed by clap, because short option
+    // only accept char, `normalize` rewrites it to --plugin beforehand.
+    // the plugin option is quite useless in linking, so
     // we are safe to ignore this option
-    #[arg(short = 'p')]
+    #[arg(long)]
     pub plugin: Option<String>,
     #[arg(long)]
     pub plugin_opt: Option<Vec<String>>,
@@ -34,11 +36,84 @@ pub struct Args {
     #[arg(short = 'z')]
     pub keyword: Option<Vec<String>>,
 
-    // clap cannot parse -static, this is a workaround
-    #[arg(short = 's', value_name = "tatic")]
-    pub static_: Option<String>,
+    // -static, see `Args::normalize`
+    #[arg(long = "static")]
+    pub static_: bool,
+
+    // omit .symtab and .strtab, and the debug sections
+    #[arg(short = 's', long)]
+    pub strip_all: bool,
+    // omit the debug sections
+    #[arg(short = 'S', long)]
+    pub strip_debug: bool,
+    // omit the local symbols from .symtab
+    #[arg(short = 'x', long)]
+    pub discard_all: bool,
+    // omit the temporary local symbols, those starting with .L
+    #[arg(short = 'X', long)]
+    pub discard_locals: bool,
+
+    // --as-needed and --no-as-needed are positional, they only affect
+    // the libraries following them. we record every occurrence so that
+    // `Args::inputs` can recover their position on the command line.
+    #[arg(long, action = ArgAction::Append, num_args = 0, default_missing_value = "true")]
+    pub as_needed: Vec<bool>,
+    #[arg(long, action = ArgAction::Append, num_args = 0, default_missing_value = "true")]
+    pub no_as_needed: Vec<bool>,
+    // gcc wraps --as-needed in --push-state/--pop-state
+    #[arg(long, action = ArgAction::Append, num_args = 0, default_missing_value = "true")]
+    pub push_state: Vec<bool>,
+    #[arg(long, action = ArgAction::Append, num_args = 0, default_missing_value = "true")]
+    pub pop_state: Vec<bool>,
+
+    // -shared, -pie and -no-pie are rewritten to the double dash
+    // form by `normalize`
+    #[arg(long)]
+    pub shared: bool,
+    #[arg(long, overrides_with = "no_pie")]
+    pub pie: bool,
+    #[arg(long, overrides_with = "pie")]
+    pub no_pie: bool,
+    #[arg(long)]
+    pub dynamic_linker: Option<String>,
+    #[arg(long)]
+    pub soname: Option<String>,
+    #[arg(long)]
+    pub version_script: Option<PathBuf>,
+
+    #[arg(long)]
+    pub no_relax: bool,
+
+    #[arg(long, overrides_with = "no_gc_sections")]
+    pub gc_sections: bool,
+    #[arg(long, overrides_with = "gc_sections")]
+    pub no_gc_sections: bool,
+    // symbols to treat as referenced, they pull archive members in and
+    // are roots of --gc-sections
+    #[arg(short = 'u', long)]
+    pub undefined: Option<Vec<String>>,
     #[arg(long)]
-    pub as_needed: bool,
+    pub print_gc_sections: bool,
+    // symbol globs to explain the liveness of
+    #[arg(long)]
+    pub why_live: Option<Vec<String>>,
+
+    // identical code folding, one of all, safe and none
+    #[arg(long, value_name = "all|safe|none")]
+    pub icf: Option<String>,
+    #[arg(long)]
+    pub print_icf_sections: bool,
+
+    #[arg(long)]
+    pub eh_frame_hdr: bool,
+
+    #[arg(long)]
+    pub gdb_index: bool,
+
+    // compress the .debug_* sections of the output
+    #[arg(long, value_name = "none|zlib|zstd")]
+    pub compress_debug_sections: Option<String>,
+
     #[arg(long)]
     pub build_id: bool,
     #[arg(long)]
@@ -46,3 +121,110 @@ pub struct Args {
     #[arg(long)]
     pub end_group: bool,
 }
+
+/// An input file or a positional option, in command line order.
+#[derive(Debug, Clone)]
+pub enum Input {
+    Object(PathBuf),
+    Library(String),
+    AsNeeded(bool),
+    PushState,
+    PopState,
+}
+
+impl Args {
+    /// Rewrite the single dash long options that gcc passes to the linker
+    /// into a form clap understands.
+    pub fn normalize<I: IntoIterator<Item = String>>(args: I) -> Vec<String> {
+        args.into_iter()
+            .map(|arg| match arg.as_str() {
+                "-plugin" => "--plugin".to_string(),
+                "-shared" => "--shared".to_string(),
+                "-static" => "--static".to_string(),
+                "-soname" => "--soname".to_string(),
+                "-pie" => "--pie".to_string(),
+                "-no-pie" => "--no-pie".to_string(),
+                "-dynamic-linker" => "--dynamic-linker".to_string(),
+                _ => arg,
+            })
+            .collect()
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.static_
+    }
+
+    pub fn strips_debug(&self) -> bool {
+        self.strip_debug || self.strip_all
+    }
+
+    /// Whether `-z <keyword>` is given.
+    pub fn has_keyword(&self, keyword: &str) -> bool {
+        self.keyword
+            .as_ref()
+            .is_some_and(|keywords| keywords.iter().any(|k| k == keyword))
+    }
+
+    /// The tombstone written by the relocations of the non-allocated
+    /// section `name` that refer to a discarded section, if any: the
+    /// last `-z dead-reloc-in-nonalloc=<glob>=<value>` matching `name`,
+    /// or 0 for the debug sections but .debug_ranges and .debug_loc. A
+    /// zero pair ends their lists and a -1 begin selects a base address,
+    /// so they get 1, which makes an empty range.
+    pub fn dead_reloc_in_nonalloc(&self, name: &str) -> Option<u64> {
+        let mut options = self.keyword.iter().flatten().filter_map(|k| {
+            let (pattern, value) = k
+                .strip_prefix("dead-reloc-in-nonalloc=")?
+                .rsplit_once('=')?;
+            let value = match value.strip_prefix("0x") {
+                Some(hex) => u64::from_str_radix(hex, 16),
+                None => value.parse::<i64>().map(|v| v as u64),
+            };
+            let value = value.unwrap_or_else(|_| panic!("invalid -z {k}"));
+            Some((pattern, value))
+        });
+        if let Some((_, value)) = options.rfind(|(pattern, _)| glob_match(pattern, name)) {
+            return Some(value);
+        }
+        match name {
+            ".debug_ranges" | ".debug_loc" => Some(1),
+            _ if name.starts_with(".debug") => Some(0),
+            _ => None,
+        }
+    }
+
+    /// Collect the objects, the libraries and the positional options
+    /// in the order they appear on the command line.
+    pub fn inputs(&self, matches: &ArgMatches) -> Vec<Input> {
+        fn indices(matches: &ArgMatches, id: &str) -> Vec<usize> {
+            matches
+                .indices_of(id)
+                .map(|i| i.collect())
+                .unwrap_or_default()
+        }
+
+        let mut inputs = vec![];
+        for (ind, obj) in indices(matches, "objects").into_iter().zip(&self.objects) {
+            inputs.push((ind, Input::Object(obj.clone())));
+        }
+        if let Some(library) = &self.library {
+            for (ind, lib) in indices(matches, "library").into_iter().zip(library) {
+                inputs.push((ind, Input::Library(lib.clone())));
+            }
+        }
+        for ind in indices(matches, "as_needed") {
+            inputs.push((ind, Input::AsNeeded(true)));
+        }
+        for ind in indices(matches, "no_as_needed") {
+            inputs.push((ind, Input::AsNeeded(false)));
+        }
+        for ind in indices(matches, "push_state") {
+            inputs.push((ind, Input::PushState));
+        }
+        for ind in indices(matches, "pop_state") {
+            inputs.push((ind, Input::PopState));
+        }
+        inputs.sort_by_key(|(ind, _)| *ind);
+        inputs.into_iter().map(|(_, input)| input).collect()
+    }
+}
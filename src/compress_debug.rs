@@ -0,0 +1,66 @@
+use std::{rc::Rc, sync::Mutex, thread};
+
+use crate::{
+    context::Context,
+    layout::has_flag,
+    linker::{SectionFlag, SectionType, ELFCOMPRESS_ZLIB, ELFCOMPRESS_ZSTD},
+    output_section::{output_section::OutputSection, synthetic_section::SyntheticSection},
+    utils::compress::compress_section,
+};
+
+impl Context {
+    /// Replace the .debug_* output sections by SHF_COMPRESSED ones, whose
+    /// contents are compressed in parallel, and move the non-alloc
+    /// sections to their new offsets.
+    pub fn compress_debug_sections(&mut self) {
+        let typ = match self.args.compress_debug_sections.as_deref() {
+            None | Some("none") => return,
+            Some("zlib") => ELFCOMPRESS_ZLIB,
+            Some("zstd") => ELFCOMPRESS_ZSTD,
+            Some(mode) => panic!("unknown --compress-debug-sections mode: {mode}"),
+        };
+
+        let mut debug = vec![];
+        for &id in &self.section_order {
+            let sec = self.sections[&id].lock().unwrap();
+            let header = sec.section_header();
+            if has_flag(header, SectionFlag::ALLOC)
+                || header._type == SectionType::NOBITS
+                || header.size == 0
+                || !sec.name().starts_with(".debug")
+            {
+                continue;
+            }
+            // the relocations are applied before the compression
+            let mut buf = vec![0; header.size as usize];
+            sec.copy_buf(self, &mut buf);
+            debug.push((id, header.add_align, buf));
+        }
+        if debug.is_empty() {
+            return;
+        }
+
+        let compressed = thread::scope(|s| {
+            let handles = debug
+                .iter()
+                .map(|(_, align, buf)| s.spawn(move || compress_section(typ, *align, buf)))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        for ((id, _, _), data) in debug.into_iter().zip(compressed) {
+            let mut old = self.sections[&id].lock().unwrap();
+            let header = old.section_header().clone();
+            let flags = header.flags | SectionFlag::COMPRESSED as u64;
+            let mut sec = SyntheticSection::new(id, &old.name(), header._type, flags, 8);
+            sec.wrapper_mut().shndx = old.wrapper_mut().shndx;
+            sec.set_data(data);
+            drop(old);
+            self.sections.insert(id, Rc::new(Mutex::new(sec)));
+        }
+        self.update_non_alloc_offsets();
+    }
+}
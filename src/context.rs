@@ -5,10 +5,20 @@ use std::{
 };
 
 use crate::{
-    linker::SectionType,
+    argument_parser::Args,
+    linker::{ElfSymbol, ProgramHeader, SectionType},
     output_section::{
+        copyrel::CopyrelSection,
+        dynamic::DynamicSection,
+        dynsym::DynsymSection,
+        eh_frame::EhFrameSection,
+        eh_frame_hdr::EhFrameHdrSection,
+        got::{GotPltSection, GotSection},
         merged_section::MergedSection,
-        output_section::{SectionWrapper, ShareOutputSection},
+        output_section::{OutputSection, SectionWrapper, ShareOutputSection},
+        plt::{IpltSection, PltSection},
+        rela::RelaSection,
+        synthetic_section::SyntheticSection,
     },
     symbol::{ShareSymbol, Symbol},
     utils::input_elf::InputElf,
@@ -16,19 +26,70 @@ use crate::{
 };
 
 pub struct Context {
+    pub args: Args,
     objects: HashMap<usize, Rc<Mutex<InputElf>>>,
     symbol_map: HashMap<String, ShareSymbol>,
     pub sections: HashMap<usize, ShareOutputSection>,
+    // symbols in the output .dynsym, in order
+    pub dynsyms: Vec<ShareSymbol>,
+    pub dynsym: Option<Rc<Mutex<DynsymSection>>>,
+    pub got: Option<Rc<Mutex<GotSection>>>,
+    pub got_plt: Option<Rc<Mutex<GotPltSection>>>,
+    pub plt: Option<Rc<Mutex<PltSection>>>,
+    pub iplt: Option<Rc<Mutex<IpltSection>>>,
+    pub rela_dyn: Option<Rc<Mutex<RelaSection>>>,
+    pub rela_plt: Option<Rc<Mutex<RelaSection>>>,
+    pub rela_iplt: Option<Rc<Mutex<RelaSection>>>,
+    pub copyrel: Option<Rc<Mutex<CopyrelSection>>>,
+    pub copyrel_relro: Option<Rc<Mutex<CopyrelSection>>>,
+    pub interp: Option<Rc<Mutex<SyntheticSection>>>,
+    pub dynamic: Option<Rc<Mutex<DynamicSection>>>,
+    pub eh_frame: Option<Rc<Mutex<EhFrameSection>>>,
+    pub eh_frame_hdr: Option<Rc<Mutex<EhFrameHdrSection>>>,
+    pub gdb_index: Option<Rc<Mutex<SyntheticSection>>>,
+    pub symtab: Option<Rc<Mutex<SyntheticSection>>>,
+    pub strtab: Option<Rc<Mutex<SyntheticSection>>>,
+    // set by `layout`: the output sections in file order, their
+    // addresses, the program headers and the TLS template boundaries
+    pub section_order: Vec<usize>,
+    pub section_addrs: HashMap<usize, u64>,
+    pub phdrs: Vec<ProgramHeader>,
+    pub tls_begin: u64,
+    pub tls_end: u64,
     obj_id: usize,
     sec_id: usize,
 }
 
 impl Context {
-    pub fn new() -> Self {
+    pub fn new(args: Args) -> Self {
         Self {
+            args,
             objects: HashMap::default(),
             symbol_map: HashMap::default(),
             sections: HashMap::default(),
+            dynsyms: vec![],
+            dynsym: None,
+            got: None,
+            got_plt: None,
+            plt: None,
+            iplt: None,
+            rela_dyn: None,
+            rela_plt: None,
+            rela_iplt: None,
+            copyrel: None,
+            copyrel_relro: None,
+            interp: None,
+            dynamic: None,
+            eh_frame: None,
+            eh_frame_hdr: None,
+            gdb_index: None,
+            symtab: None,
+            strtab: None,
+            section_order: vec![],
+            section_addrs: HashMap::default(),
+            phdrs: vec![],
+            tls_begin: 0,
+            tls_end: 0,
             obj_id: 1,
             sec_id: 1,
         }
@@ -48,9 +109,35 @@ impl Context {
     pub fn object_iter(&self) -> Values<'_, usize, Rc<Mutex<InputElf>>> {
         self.objects.values()
     }
+    /// The objects in command line order.
+    pub fn objects_in_order(&self) -> Vec<Rc<Mutex<InputElf>>> {
+        let mut ids = self.objects.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+        ids.into_iter()
+            .map(|id| self.objects[&id].clone())
+            .collect()
+    }
     pub fn get_object(&self, id: usize) -> Option<Rc<Mutex<InputElf>>> {
         self.objects.get(&id).map(|n| n.clone())
     }
+    /// The sonames of the shared libraries that stay alive after
+    /// `mark_live_objects`, in command line order.
+    pub fn dt_needed(&self) -> Vec<String> {
+        let mut dsos = self
+            .objects
+            .iter()
+            .filter_map(|(id, obj)| {
+                let obj = obj.lock().unwrap();
+                if obj.is_dso && obj.is_alive {
+                    Some((*id, obj.soname.clone()))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        dsos.sort_by_key(|(id, _)| *id);
+        dsos.into_iter().map(|(_, soname)| soname).collect()
+    }
     pub fn reclaim_objects(&mut self) {
         // clear objects
         let arr = self
@@ -75,7 +162,7 @@ impl Context {
             .iter()
             .filter(|(_, symbol)| {
                 let sym = symbol.lock().unwrap();
-                sym.is_alive
+                !sym.is_alive
             })
             .map(|(s, _)| s.clone())
             .collect::<Vec<_>>();
@@ -83,6 +170,12 @@ impl Context {
             self.symbol_map.remove(&s);
         }
     }
+    pub fn symbol_names(&self) -> Vec<String> {
+        self.symbol_map.keys().cloned().collect()
+    }
+    pub fn get_symbol(&self, name: &str) -> Option<ShareSymbol> {
+        self.symbol_map.get(name).cloned()
+    }
     pub fn find_symbol_by_name(&mut self, name: String) -> ShareSymbol {
         if self.symbol_map.contains_key(&name) {
             self.symbol_map[&name].clone()
@@ -92,6 +185,101 @@ impl Context {
             sym
         }
     }
+    pub fn add_output_section<T, F>(&mut self, f: F) -> Rc<Mutex<T>>
+    where
+        T: OutputSection + 'static,
+        F: FnOnce(usize) -> T,
+    {
+        let id = self.sec_id;
+        self.sec_id += 1;
+        let sec = Rc::new(Mutex::new(f(id)));
+        self.sections.insert(id, sec.clone());
+        sec
+    }
+    pub fn add_synthetic_section(
+        &mut self,
+        name: &str,
+        typ: SectionType,
+        flags: u64,
+        align: u64,
+    ) -> Rc<Mutex<SyntheticSection>> {
+        self.add_output_section(|id| SyntheticSection::new(id, name, typ, flags, align))
+    }
+    /// Whether the output is position independent.
+    pub fn is_pic(&self) -> bool {
+        self.args.shared || self.args.pie
+    }
+    /// Whether the general and local dynamic TLS accesses are rewritten
+    /// to the initial and local exec models, which only executables can use.
+    pub fn relax_tls(&self) -> bool {
+        !self.args.shared && !self.args.no_relax
+    }
+    /// The symbol table entry that defines `sym`.
+    pub fn elf_symbol(&self, sym: &Symbol) -> ElfSymbol {
+        let obj = self.get_object(sym.elf.unwrap()).unwrap();
+        let obj = obj.lock().unwrap();
+        obj.symbol_info.as_ref().unwrap().elf_symbols[sym.index].clone()
+    }
+    pub fn copyrel_section(&self, is_relro: bool) -> Rc<Mutex<CopyrelSection>> {
+        if is_relro {
+            self.copyrel_relro.clone().unwrap()
+        } else {
+            self.copyrel.clone().unwrap()
+        }
+    }
+    pub fn copyrel_addr(&self, sym: &Symbol) -> u64 {
+        let (is_relro, offset) = sym.copyrel.unwrap();
+        let sec = self.copyrel_section(is_relro);
+        let sec = sec.lock().unwrap();
+        sec.addr(self) + offset as u64
+    }
+    /// The address of `sym` as seen by the code referencing it: the
+    /// PLT entry of a function, the copy of a data object, etc.
+    pub fn symbol_addr(&self, sym: &Symbol) -> u64 {
+        self.linker_symbol_addr(sym)
+            .unwrap_or_else(|| self.definition_addr(sym))
+    }
+    /// The address of the symbols the linker redirects to its own sections.
+    pub fn linker_symbol_addr(&self, sym: &Symbol) -> Option<u64> {
+        if sym.is_iplt() {
+            let iplt = self.iplt.as_ref().unwrap().lock().unwrap();
+            return Some(iplt.entry_addr(sym.plt_idx.unwrap()));
+        }
+        if sym.copyrel.is_some() {
+            return Some(self.copyrel_addr(sym));
+        }
+        if let (true, Some(idx)) = (sym.is_imported, sym.plt_idx) {
+            let plt = self.plt.as_ref().unwrap().lock().unwrap();
+            return Some(plt.entry_addr(idx));
+        }
+        if let Some((id, offset)) = sym.synthetic {
+            return Some(self.section_addrs[&id].wrapping_add(offset));
+        }
+        None
+    }
+    /// The address of the definition of `sym` in an input section, 0 for
+    /// undefined and imported symbols.
+    pub fn definition_addr(&self, sym: &Symbol) -> u64 {
+        match sym.elf {
+            Some(id) if sym.is_alive && !sym.is_imported => {
+                let obj = self.get_object(id).unwrap();
+                let obj = obj.lock().unwrap();
+                obj.defined_addr(self, sym.index)
+            }
+            _ => 0,
+        }
+    }
+    /// `_GLOBAL_OFFSET_TABLE_`, the start of .got.plt, or of .got for
+    /// outputs without a PLT.
+    pub fn got_base(&self) -> u64 {
+        if let Some(ref got_plt) = self.got_plt {
+            got_plt.lock().unwrap().section_header().addr
+        } else if let Some(ref got) = self.got {
+            got.lock().unwrap().section_header().addr
+        } else {
+            0
+        }
+    }
     pub fn find_mergeable_section(
         &mut self,
         name: String,
@@ -100,13 +288,17 @@ impl Context {
     ) -> ShareOutputSection {
         for sec in self.sections.values() {
             let sec_guard = sec.lock().unwrap();
-            if name == sec_guard.name() && typ == sec_guard.typ() && flags == sec_guard.flags() {
+            if sec_guard.is_mergeable()
+                && name == sec_guard.name()
+                && typ == sec_guard.typ()
+                && flags == sec_guard.flags()
+            {
                 return sec.clone();
             }
         }
         let id = self.sec_id;
         self.sec_id += 1;
-        let sec = SectionWrapper::new(id);
+        let sec = SectionWrapper::new_with(id, &name, typ, flags, 1);
         let sec = MergedSection::new(sec);
         self.sections.insert(id, sec.clone());
         sec
@@ -0,0 +1,60 @@
+use crate::{
+    context::Context,
+    linker::{elf_hash, SectionFlag, SectionType},
+    output_section::dynamic::DynamicSection,
+};
+
+impl Context {
+    /// Create .interp, .hash and .dynamic, which a dynamic output needs
+    /// to be loaded. `needed` and `soname` are offsets in .dynstr.
+    pub fn create_dynamic_section(&mut self, needed: Vec<u32>, soname: Option<u32>) {
+        let alloc = SectionFlag::ALLOC as u64;
+        if let (false, Some(path)) = (self.args.shared, self.args.dynamic_linker.clone()) {
+            let interp = self.add_synthetic_section(".interp", SectionType::PROGBITS, alloc, 1);
+            let mut data = path.into_bytes();
+            data.push(0);
+            interp.lock().unwrap().set_data(data);
+            self.interp = Some(interp);
+        }
+
+        // the dynamic loader looks the symbols of the output up through
+        // the hash table, e.g. to bind libc to the copy of `environ`
+        let names = self
+            .dynsyms
+            .iter()
+            .map(|sym| {
+                let sym = sym.lock().unwrap();
+                sym.name.split('@').next().unwrap().to_string()
+            })
+            .collect::<Vec<_>>();
+        let hash = self.add_synthetic_section(".hash", SectionType::HASH, alloc, 4);
+        let mut hash = hash.lock().unwrap();
+        hash.section_header_mut().ent_size = 4;
+        hash.set_data(build_hash(&names));
+        drop(hash);
+
+        self.dynamic = Some(self.add_output_section(|id| DynamicSection::new(id, needed, soname)));
+    }
+}
+
+/// The SysV hash table of .dynsym: the number of buckets and of chains,
+/// the buckets holding the first symbol of their chain, then the next
+/// symbol in the chain of each symbol. `names` skips the null symbol.
+fn build_hash(names: &[String]) -> Vec<u8> {
+    let nchain = names.len() + 1;
+    let nbucket = (names.len() / 2).max(1);
+    let mut buckets = vec![0u32; nbucket];
+    let mut chains = vec![0u32; nchain];
+    for (i, name) in names.iter().enumerate() {
+        let idx = i as u32 + 1;
+        let bucket = elf_hash(name) as usize % nbucket;
+        chains[idx as usize] = buckets[bucket];
+        buckets[bucket] = idx;
+    }
+    [nbucket as u32, nchain as u32]
+        .into_iter()
+        .chain(buckets)
+        .chain(chains)
+        .flat_map(u32::to_le_bytes)
+        .collect()
+}
@@ -0,0 +1,263 @@
+use std::{collections::HashMap, ops::Range};
+
+use crate::{
+    context::Context,
+    linker::{SectionIndex, SectionType},
+    output_section::{
+        eh_frame::{EhFramePiece, EhFrameSection},
+        eh_frame_hdr::EhFrameHdrSection,
+        output_section::OutputSection,
+    },
+    section::SectionId,
+    symbol::ShareSymbol,
+    utils::input_elf::InputElf,
+};
+
+/// A CIE or an FDE of an input .eh_frame.
+pub struct EhFrameRecord {
+    // the offset in the section and the size, including the length field
+    pub offset: u64,
+    pub size: u64,
+    // indices into the relocations of the section
+    pub rels: Range<usize>,
+}
+
+pub struct Fde {
+    pub record: EhFrameRecord,
+    // the index of its CIE in `InputEhFrame::cies`
+    pub cie: usize,
+}
+
+/// The records of the .eh_frame of an object.
+pub struct InputEhFrame {
+    pub shndx: usize,
+    pub cies: Vec<EhFrameRecord>,
+    pub fdes: Vec<Fde>,
+}
+
+/// The function an FDE describes, a global symbol is resolved once the
+/// object is unlocked.
+enum FdeTarget {
+    Section(SectionId),
+    Symbol(ShareSymbol),
+}
+
+/// What a relocation of a CIE refers to, CIEs are identical when their
+/// contents and relocations are.
+#[derive(PartialEq, Eq, Hash)]
+enum CieTarget {
+    Global(String),
+    Local(usize, usize),
+}
+
+/// The contents of a CIE and its relocations, as (offset in the record,
+/// type, addend, target).
+type CieKey = (Vec<u8>, Vec<(u64, u32, i64, CieTarget)>);
+
+impl Context {
+    pub fn parse_eh_frames(&mut self) {
+        for obj in self.objects_in_order() {
+            let mut obj = obj.lock().unwrap();
+            if !obj.is_dso {
+                obj.parse_eh_frame();
+            }
+        }
+    }
+
+    /// Create the output .eh_frame from the records of the input ones,
+    /// which stay out of the regular output sections.
+    pub fn create_eh_frame_section(&mut self) {
+        let objects = self
+            .objects_in_order()
+            .into_iter()
+            .filter(|obj| obj.lock().unwrap().eh_frame.is_some())
+            .collect::<Vec<_>>();
+        if objects.is_empty() {
+            return;
+        }
+        let eh_frame = self.add_output_section(EhFrameSection::new);
+        let mut eh_frame_guard = eh_frame.lock().unwrap();
+        let mut cie_offsets = HashMap::new();
+        for obj in objects {
+            let targets = obj.lock().unwrap().fde_targets();
+            let live = targets
+                .into_iter()
+                .map(|target| target.is_some_and(|target| self.is_live_fde_target(target)))
+                .collect::<Vec<_>>();
+
+            let mut obj = obj.lock().unwrap();
+            let frame = obj.eh_frame.as_ref().unwrap();
+            let shndx = frame.shndx;
+            let first = eh_frame_guard.section_header().size;
+            for (fde, _) in frame.fdes.iter().zip(live).filter(|(_, live)| *live) {
+                let cie = &frame.cies[fde.cie];
+                let cie_out_offset = *cie_offsets
+                    .entry(obj.cie_key(shndx, cie))
+                    .or_insert_with(|| eh_frame_guard.add(obj.eh_frame_piece(shndx, cie, None)));
+                eh_frame_guard.add(obj.eh_frame_piece(shndx, &fde.record, Some(cie_out_offset)));
+            }
+            // symbols in the input section, e.g. __EH_FRAME_BEGIN__ of
+            // crtbegin.o, point into the output one
+            let sec = obj.section_info.sections[shndx].as_mut().unwrap();
+            sec.out_sec = Some(eh_frame_guard.id());
+            sec.offset = first;
+        }
+        eh_frame_guard.add_terminator();
+        let num_fdes = eh_frame_guard.num_fdes();
+        drop(eh_frame_guard);
+        self.eh_frame = Some(eh_frame);
+        if self.args.eh_frame_hdr {
+            let hdr = self.add_output_section(|id| EhFrameHdrSection::new(id, num_fdes));
+            self.eh_frame_hdr = Some(hdr);
+        }
+    }
+
+    /// Whether the function of an FDE is in the output, it's not if its
+    /// section was discarded or folded into another one.
+    fn is_live_fde_target(&self, target: FdeTarget) -> bool {
+        let (id, shndx) = match target {
+            FdeTarget::Section(id) => id,
+            FdeTarget::Symbol(sym) => {
+                let sym = sym.lock().unwrap();
+                let Some(id) = sym.elf.filter(|_| !sym.is_imported) else {
+                    return false;
+                };
+                let Some(obj) = self.get_object(id) else {
+                    return false;
+                };
+                let obj = obj.lock().unwrap();
+                match obj.symbol_info.as_ref().unwrap().elf_symbols[sym.index].index() {
+                    SectionIndex::Other(shndx) => (id, shndx as usize),
+                    _ => return false,
+                }
+            }
+        };
+        let obj = self.get_object(id).unwrap();
+        let obj = obj.lock().unwrap();
+        !obj.is_discarded(shndx) && !obj.is_folded(shndx)
+    }
+}
+
+impl InputElf {
+    /// Split .eh_frame into its CIEs and FDEs.
+    pub fn parse_eh_frame(&mut self) {
+        let Some(shndx) = self
+            .section_info
+            .elf_sections
+            .iter()
+            .enumerate()
+            .position(|(i, sec)| {
+                !self.is_discarded(i)
+                    && (sec._type == SectionType::X86_64_UNWIND
+                        || self.section_info.sections[i].as_ref().unwrap().name == ".eh_frame")
+            })
+        else {
+            return;
+        };
+        let rels = &mut self.section_info.relocations[shndx];
+        rels.sort_by_key(|rel| rel.offset);
+        let data = &self.section_info.sections[shndx].as_ref().unwrap().data;
+
+        let mut frame = InputEhFrame {
+            shndx,
+            cies: vec![],
+            fdes: vec![],
+        };
+        let mut cie_indices = HashMap::new();
+        let mut offset = 0;
+        while offset + 4 <= data.len() {
+            let word = |at: usize| u32::from_le_bytes(data[at..at + 4].try_into().unwrap());
+            let len = word(offset) as usize;
+            if len == 0 {
+                // the terminator, as in crtend.o
+                break;
+            }
+            if len == 0xffffffff {
+                panic!("{}: 64-bit .eh_frame records are not supported", self.name);
+            }
+            let end = offset + 4 + len;
+            let start_rel = rels.partition_point(|rel| rel.offset < offset as u64);
+            let end_rel = rels.partition_point(|rel| rel.offset < end as u64);
+            let record = EhFrameRecord {
+                offset: offset as u64,
+                size: (end - offset) as u64,
+                rels: start_rel..end_rel,
+            };
+            // the CIE pointer of an FDE is the distance back to its CIE
+            let id = word(offset + 4) as usize;
+            if id == 0 {
+                cie_indices.insert(offset, frame.cies.len());
+                frame.cies.push(record);
+            } else {
+                let cie_offset = offset + 4 - id;
+                let Some(&cie) = cie_indices.get(&cie_offset) else {
+                    panic!("{}: FDE at {:#x} has no CIE", self.name, offset);
+                };
+                frame.fdes.push(Fde { record, cie });
+            }
+            offset = end;
+        }
+        self.eh_frame = Some(frame);
+    }
+
+    /// The function described by each FDE, given by the relocation of
+    /// its first field.
+    fn fde_targets(&self) -> Vec<Option<FdeTarget>> {
+        let frame = self.eh_frame.as_ref().unwrap();
+        let rels = &self.section_info.relocations[frame.shndx];
+        let info = self.symbol_info.as_ref().unwrap();
+        frame
+            .fdes
+            .iter()
+            .map(|fde| {
+                let rel = rels[fde.record.rels.clone()]
+                    .iter()
+                    .find(|rel| rel.offset == fde.record.offset + 8)?;
+                if rel.sym() >= info.first_global {
+                    return Some(FdeTarget::Symbol(info.symbol(rel.sym())));
+                }
+                match info.elf_symbols[rel.sym()].index() {
+                    SectionIndex::Other(shndx) => {
+                        Some(FdeTarget::Section((self.id, shndx as usize)))
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    fn cie_key(&self, shndx: usize, cie: &EhFrameRecord) -> CieKey {
+        let data = &self.section_info.sections[shndx].as_ref().unwrap().data;
+        let info = self.symbol_info.as_ref().unwrap();
+        let rels = self.section_info.relocations[shndx][cie.rels.clone()]
+            .iter()
+            .map(|rel| {
+                let target = if rel.sym() >= info.first_global {
+                    CieTarget::Global(info.symbol(rel.sym()).lock().unwrap().name.clone())
+                } else {
+                    CieTarget::Local(self.id, rel.sym())
+                };
+                (rel.offset - cie.offset, rel.typ(), rel.addend, target)
+            })
+            .collect();
+        let range = cie.offset as usize..(cie.offset + cie.size) as usize;
+        (data[range].to_vec(), rels)
+    }
+
+    fn eh_frame_piece(
+        &self,
+        shndx: usize,
+        record: &EhFrameRecord,
+        cie_out_offset: Option<u64>,
+    ) -> EhFramePiece {
+        EhFramePiece {
+            obj: self.id,
+            shndx,
+            offset: record.offset,
+            size: record.size,
+            rels: record.rels.clone(),
+            out_offset: 0,
+            cie_out_offset,
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::{hash_map::Entry, HashMap, VecDeque};
+
+use crate::{
+    context::Context,
+    linker::{SectionFlag, SectionIndex, SectionType, SymbolType},
+    section::SectionId,
+    symbol::ShareSymbol,
+    utils::{glob::glob_match, input_elf::InputElf},
+};
+
+/// Where a relocation points to. Global symbols are resolved only after
+/// the referring object is unlocked, the definition may be in the same
+/// object.
+enum Edge {
+    Section(SectionId),
+    Symbol(ShareSymbol),
+}
+
+/// Why a section is live, for --why-live.
+#[derive(Clone)]
+enum Reason {
+    Root(String),
+    ReferencedBy(SectionId),
+}
+
+#[derive(Default)]
+struct Graph {
+    roots: Vec<(Edge, String)>,
+    edges: HashMap<SectionId, Vec<Edge>>,
+    // input sections by name, for __start_ and __stop_ references
+    by_name: HashMap<String, Vec<SectionId>>,
+    candidates: Vec<SectionId>,
+    // "file:(section)" of the candidates, for the diagnostics
+    names: HashMap<SectionId, String>,
+}
+
+impl Context {
+    /// Drop the allocated input sections that cannot be reached through
+    /// relocations from the entry point, the `-u` symbols, the exported
+    /// symbols and the sections that have to be kept.
+    pub fn gc_sections(&mut self) {
+        if !self.args.gc_sections {
+            return;
+        }
+        let mut graph = Graph::default();
+        for obj in self.objects_in_order() {
+            let obj = obj.lock().unwrap();
+            if !obj.is_dso {
+                obj.collect_gc_edges(&mut graph);
+            }
+        }
+
+        let mut names = vec![("_start".to_string(), "the entry point".to_string())];
+        for name in self.args.undefined.iter().flatten() {
+            names.push((name.clone(), format!("-u {name}")));
+        }
+        for (name, reason) in names {
+            if let Some(sym) = self.get_symbol(&name) {
+                graph.roots.push((Edge::Symbol(sym), reason));
+            }
+        }
+
+        let mut live = HashMap::new();
+        let mut queue = VecDeque::new();
+        for (edge, reason) in &graph.roots {
+            let reason = Reason::Root(reason.clone());
+            self.mark_edge(edge, reason, &graph, &mut live, &mut queue);
+        }
+        while let Some(id) = queue.pop_front() {
+            for edge in graph.edges.get(&id).into_iter().flatten() {
+                let reason = Reason::ReferencedBy(id);
+                self.mark_edge(edge, reason, &graph, &mut live, &mut queue);
+            }
+        }
+
+        if let Some(ref patterns) = self.args.why_live {
+            self.print_why_live(patterns, &graph, &live);
+        }
+
+        for id in graph.candidates {
+            if !live.contains_key(&id) {
+                if self.args.print_gc_sections {
+                    println!("removing unused section {}", graph.names[&id]);
+                }
+                let obj = self.get_object(id.0).unwrap();
+                obj.lock().unwrap().discard_section(id.1);
+            }
+        }
+    }
+
+    /// Print the chain of references from a root to the section of each
+    /// symbol matching one of `patterns`.
+    fn print_why_live(
+        &self,
+        patterns: &[String],
+        graph: &Graph,
+        live: &HashMap<SectionId, Reason>,
+    ) {
+        let mut matches = vec![];
+        for obj in self.objects_in_order() {
+            let obj = obj.lock().unwrap();
+            if !obj.is_dso {
+                obj.collect_symbols_matching(patterns, &mut matches);
+            }
+        }
+
+        for (name, id) in matches {
+            let Some(sec) = graph.names.get(&id) else {
+                println!("{name} is in a section that is never collected");
+                continue;
+            };
+            let Some(mut reason) = live.get(&id) else {
+                println!("{name} in {sec} is not live");
+                continue;
+            };
+            println!("live symbol: {name} in {sec}");
+            while let Reason::ReferencedBy(from) = reason {
+                println!(">>> referenced by {}", graph.names[from]);
+                reason = &live[from];
+            }
+            if let Reason::Root(root) = reason {
+                println!(">>> kept alive by {root}");
+            }
+        }
+    }
+
+    fn mark_edge(
+        &self,
+        edge: &Edge,
+        reason: Reason,
+        graph: &Graph,
+        live: &mut HashMap<SectionId, Reason>,
+        queue: &mut VecDeque<SectionId>,
+    ) {
+        let targets = match edge {
+            Edge::Section(id) => vec![*id],
+            Edge::Symbol(sym) => {
+                let sym = sym.lock().unwrap();
+                match sym.elf {
+                    Some(id) if !sym.is_imported => {
+                        // e.g. a symbol defined by the linker
+                        let Some(obj) = self.get_object(id) else {
+                            return;
+                        };
+                        let obj = obj.lock().unwrap();
+                        let info = obj.symbol_info.as_ref().unwrap();
+                        match info.elf_symbols[sym.index].index() {
+                            SectionIndex::Other(shndx) => vec![(id, shndx as usize)],
+                            _ => vec![],
+                        }
+                    }
+                    Some(_) => vec![],
+                    // an undefined __start_foo or __stop_foo keeps every
+                    // section named foo
+                    None => sym
+                        .name
+                        .strip_prefix("__start_")
+                        .or_else(|| sym.name.strip_prefix("__stop_"))
+                        .and_then(|name| graph.by_name.get(name))
+                        .cloned()
+                        .unwrap_or_default(),
+                }
+            }
+        };
+        for target in targets {
+            if let Entry::Vacant(entry) = live.entry(target) {
+                entry.insert(reason.clone());
+                queue.push_back(target);
+            }
+        }
+    }
+}
+
+impl InputElf {
+    fn collect_gc_edges(&self, graph: &mut Graph) {
+        let Some(ref info) = self.symbol_info else {
+            return;
+        };
+        let edge = |sym: usize| {
+            if sym >= info.first_global {
+                return Some(Edge::Symbol(info.symbol(sym)));
+            }
+            match info.elf_symbols[sym].index() {
+                SectionIndex::Other(shndx) => Some(Edge::Section((self.id, shndx as usize))),
+                _ => None,
+            }
+        };
+
+        for (i, header) in self.section_info.elf_sections.iter().enumerate() {
+            let Some(ref sec) = self.section_info.sections[i] else {
+                continue;
+            };
+            // non-allocated sections are kept but do not keep anything
+            // alive, and mergeable sections are always kept
+            if header.flags & SectionFlag::ALLOC as u64 == 0
+                || header.flags & SectionFlag::MERGE as u64 != 0
+            {
+                continue;
+            }
+            if self.eh_frame.as_ref().is_some_and(|frame| frame.shndx == i) {
+                self.collect_eh_frame_edges(&edge, graph);
+                continue;
+            }
+            let rels = &self.section_info.relocations[i];
+
+            let id = (self.id, i);
+            graph.candidates.push(id);
+            graph
+                .names
+                .insert(id, format!("{}:({})", self.name, sec.name));
+            graph.by_name.entry(sec.name.clone()).or_default().push(id);
+            graph
+                .edges
+                .entry(id)
+                .or_default()
+                .extend(rels.iter().filter_map(|rel| edge(rel.sym())));
+
+            let is_root = matches!(
+                header._type,
+                SectionType::INIT_ARRAY
+                    | SectionType::FINI_ARRAY
+                    | SectionType::PREINIT_ARRAY
+                    | SectionType::NOTE
+            ) || header.flags & SectionFlag::GNU_RETAIN as u64 != 0
+                || Self::is_kept_section(&sec.name);
+            if is_root {
+                let reason = format!("{} that is always kept", sec.name);
+                graph.roots.push((Edge::Section(id), reason));
+            }
+        }
+
+        // the symbols in .dynsym may be used by other modules, e.g. what
+        // a shared library exports or the callbacks of an executable
+        for (i, sym) in info.global_symbols.iter().enumerate() {
+            let elf_sym = &info.elf_symbols[info.first_global + i];
+            if let SectionIndex::Other(shndx) = elf_sym.index() {
+                let sym = sym.lock().unwrap();
+                if sym.is_exported && sym.elf == Some(self.id) {
+                    let reason = format!("the exported symbol {}", sym.name);
+                    graph
+                        .roots
+                        .push((Edge::Section((self.id, shndx as usize)), reason));
+                }
+            }
+        }
+    }
+
+    /// The symbols defined here whose name matches one of `patterns`,
+    /// with their section.
+    fn collect_symbols_matching(&self, patterns: &[String], out: &mut Vec<(String, SectionId)>) {
+        let Some(ref info) = self.symbol_info else {
+            return;
+        };
+        for (i, elf_sym) in info.elf_symbols.iter().enumerate() {
+            if matches!(elf_sym.typ(), SymbolType::SECTION | SymbolType::FILE) {
+                continue;
+            }
+            let SectionIndex::Other(shndx) = elf_sym.index() else {
+                continue;
+            };
+            let sym = info.symbol(i);
+            let sym = sym.lock().unwrap();
+            if i >= info.first_global && sym.elf != Some(self.id) {
+                continue;
+            }
+            if patterns.iter().any(|p| glob_match(p, &sym.name)) {
+                out.push((sym.name.clone(), (self.id, shndx as usize)));
+            }
+        }
+    }
+
+    /// The sections a linker script would wrap in KEEP.
+    pub fn is_kept_section(name: &str) -> bool {
+        [".init", ".fini", ".ctors", ".dtors", ".jcr"]
+            .iter()
+            .any(|prefix| name == *prefix || name.starts_with(&format!("{prefix}.")))
+    }
+
+    /// .eh_frame refers to every function, it is not a root itself. The
+    /// relocations of a CIE, the personality routine, are roots, and the
+    /// ones of an FDE past the function it describes, the LSDA, are
+    /// edges of that function.
+    fn collect_eh_frame_edges<F>(&self, edge: &F, graph: &mut Graph)
+    where
+        F: Fn(usize) -> Option<Edge>,
+    {
+        let frame = self.eh_frame.as_ref().unwrap();
+        let rels = &self.section_info.relocations[frame.shndx];
+        let reason = format!("a CIE of {}:(.eh_frame)", self.name);
+        for cie in &frame.cies {
+            let personality = rels[cie.rels.clone()]
+                .iter()
+                .filter_map(|rel| edge(rel.sym()));
+            graph
+                .roots
+                .extend(personality.map(|edge| (edge, reason.clone())));
+        }
+        for fde in &frame.fdes {
+            let mut record = rels[fde.record.rels.clone()].iter();
+            if let Some(Edge::Section(func)) = record.next().and_then(|rel| edge(rel.sym())) {
+                let lsda = record.filter_map(|rel| edge(rel.sym()));
+                graph.edges.entry(func).or_default().extend(lsda);
+            }
+        }
+    }
+}
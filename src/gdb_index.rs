@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+
+use crate::{
+    context::Context,
+    layout::has_flag,
+    linker::{SectionFlag, SectionType},
+    utils::input_elf::InputElf,
+};
+
+const GDB_INDEX_VERSION: u32 = 7;
+
+/// The inputs of .gdb_index, read from the input debug sections.
+#[derive(Default)]
+struct GdbIndex {
+    // (offset, size) of the compilation units in the output .debug_info
+    units: Vec<(u64, u64)>,
+    // (low, high, compilation unit) of the address ranges
+    ranges: Vec<(u64, u64, u32)>,
+    // the names, with their compilation units and symbol kinds
+    names: Vec<(String, Vec<u32>)>,
+    name_index: HashMap<String, usize>,
+}
+
+impl Context {
+    /// Create .gdb_index, filled by `write_gdb_index` once the addresses
+    /// are known. .debug_gnu_pubnames and .debug_gnu_pubtypes, which it
+    /// replaces, are not copied into the output.
+    pub fn create_gdb_index_section(&mut self) {
+        if !self.args.gdb_index || self.args.strips_debug() {
+            return;
+        }
+        let has_debug_info = self.objects_in_order().iter().any(|obj| {
+            let obj = obj.lock().unwrap();
+            let found = obj.debug_sections(".debug_info").next().is_some();
+            found
+        });
+        if has_debug_info {
+            let sec = self.add_synthetic_section(".gdb_index", SectionType::PROGBITS, 0, 4);
+            self.gdb_index = Some(sec);
+        }
+    }
+
+    /// Build the index gdb loads instead of reading the debug info of
+    /// every compilation unit at startup.
+    pub fn write_gdb_index(&self) {
+        let Some(ref sec) = self.gdb_index else {
+            return;
+        };
+        let mut index = GdbIndex::default();
+        let objects = self.objects_in_order();
+        for obj in &objects {
+            let obj = obj.lock().unwrap();
+            obj.read_units(&mut index);
+        }
+        let unit_index = index
+            .units
+            .iter()
+            .enumerate()
+            .map(|(i, &(offset, _))| (offset, i as u32))
+            .collect::<HashMap<_, _>>();
+        for obj in &objects {
+            let obj = obj.lock().unwrap();
+            obj.read_ranges(self, &unit_index, &mut index);
+            for name in [".debug_gnu_pubnames", ".debug_gnu_pubtypes"] {
+                obj.read_pubnames(self, name, &unit_index, &mut index);
+            }
+        }
+        sec.lock().unwrap().set_data(index.build());
+        self.update_non_alloc_offsets();
+    }
+}
+
+impl InputElf {
+    /// The indices of the live input sections called `name`.
+    fn debug_sections<'a>(&'a self, name: &'a str) -> impl Iterator<Item = usize> + 'a {
+        let live = self.is_alive && !self.is_dso;
+        self.section_info
+            .sections
+            .iter()
+            .enumerate()
+            .filter(move |(i, sec)| {
+                live && sec.as_ref().is_some_and(|sec| sec.name == name)
+                    && !has_flag(&self.section_info.elf_sections[*i], SectionFlag::ALLOC)
+            })
+            .map(|(i, _)| i)
+    }
+
+    /// The contents of the input section `shndx` once relocated.
+    fn relocated_data(&self, ctx: &Context, shndx: usize) -> Vec<u8> {
+        let mut data = self.section_info.sections[shndx]
+            .as_ref()
+            .unwrap()
+            .data
+            .clone();
+        self.apply_relocations(ctx, shndx, &mut data, 0);
+        data
+    }
+
+    fn read_units(&self, index: &mut GdbIndex) {
+        for shndx in self.debug_sections(".debug_info") {
+            let sec = self.section_info.sections[shndx].as_ref().unwrap();
+            if sec.out_sec.is_none() {
+                continue;
+            }
+            let mut offset = 0;
+            while offset + 4 <= sec.data.len() {
+                let length = read_u32(&sec.data, offset);
+                if length == 0xffffffff {
+                    panic!(
+                        "{}: 64-bit DWARF is not supported by --gdb-index",
+                        self.name
+                    );
+                }
+                let size = 4 + length as u64;
+                index.units.push((sec.offset + offset as u64, size));
+                offset += size as usize;
+            }
+        }
+    }
+
+    /// The address ranges of .debug_aranges, those of discarded code
+    /// start at the tombstone 0.
+    fn read_ranges(&self, ctx: &Context, unit_index: &HashMap<u64, u32>, index: &mut GdbIndex) {
+        for shndx in self.debug_sections(".debug_aranges") {
+            let data = self.relocated_data(ctx, shndx);
+            let mut set = 0;
+            while set + 12 <= data.len() {
+                let end = set + 4 + read_u32(&data, set) as usize;
+                let unit = read_u32(&data, set + 6) as u64;
+                let addr_size = data[set + 10] as usize;
+                if addr_size != 8 {
+                    panic!(
+                        "{}: unsupported .debug_aranges address size {}",
+                        self.name, addr_size
+                    );
+                }
+                // the header is padded to the size of a tuple
+                let mut offset = set + 16;
+                while offset + 16 <= end {
+                    let low = read_u64(&data, offset);
+                    let len = read_u64(&data, offset + 8);
+                    offset += 16;
+                    if low == 0 || len == 0 {
+                        continue;
+                    }
+                    if let Some(&cu) = unit_index.get(&unit) {
+                        index.ranges.push((low, low + len, cu));
+                    }
+                }
+                set = end;
+            }
+        }
+    }
+
+    /// The names of `name`, .debug_gnu_pubnames or .debug_gnu_pubtypes,
+    /// whose entries hold a DIE offset, the upper byte of the attributes
+    /// of the symbol in the index and a null terminated name.
+    fn read_pubnames(
+        &self,
+        ctx: &Context,
+        name: &str,
+        unit_index: &HashMap<u64, u32>,
+        index: &mut GdbIndex,
+    ) {
+        for shndx in self.debug_sections(name) {
+            let data = self.relocated_data(ctx, shndx);
+            let mut set = 0;
+            while set + 14 <= data.len() {
+                let end = set + 4 + read_u32(&data, set) as usize;
+                let unit = read_u32(&data, set + 6) as u64;
+                let Some(&cu) = unit_index.get(&unit) else {
+                    set = end;
+                    continue;
+                };
+                let mut offset = set + 14;
+                while offset + 4 <= end && read_u32(&data, offset) != 0 {
+                    let kind = data[offset + 4] as u32;
+                    let start = offset + 5;
+                    let len = data[start..end].iter().position(|&c| c == 0).unwrap();
+                    let name = String::from_utf8_lossy(&data[start..start + len]).into_owned();
+                    index.add_name(name, cu | kind << 24);
+                    offset = start + len + 1;
+                }
+                set = end;
+            }
+        }
+    }
+}
+
+impl GdbIndex {
+    fn add_name(&mut self, name: String, value: u32) {
+        let i = *self.name_index.entry(name.clone()).or_insert_with(|| {
+            self.names.push((name, vec![]));
+            self.names.len() - 1
+        });
+        let values = &mut self.names[i].1;
+        if !values.contains(&value) {
+            values.push(value);
+        }
+    }
+
+    /// The header, the compilation units, the (empty) type units, the
+    /// address ranges, the hash table of the names and the constant pool
+    /// of the name vectors and strings.
+    fn build(&self) -> Vec<u8> {
+        let table_size = (self.names.len() * 4 / 3 + 1).next_power_of_two();
+        let units_offset = 24;
+        let types_offset = units_offset + self.units.len() * 16;
+        let ranges_offset = types_offset;
+        let table_offset = ranges_offset + self.ranges.len() * 20;
+        let pool_offset = table_offset + table_size * 8;
+
+        let mut out = vec![];
+        for offset in [
+            GDB_INDEX_VERSION as usize,
+            units_offset,
+            types_offset,
+            ranges_offset,
+            table_offset,
+            pool_offset,
+        ] {
+            out.extend((offset as u32).to_le_bytes());
+        }
+        for &(offset, size) in &self.units {
+            out.extend(offset.to_le_bytes());
+            out.extend(size.to_le_bytes());
+        }
+        for &(low, high, cu) in &self.ranges {
+            out.extend(low.to_le_bytes());
+            out.extend(high.to_le_bytes());
+            out.extend(cu.to_le_bytes());
+        }
+
+        let mut pool = vec![];
+        let mut vectors = vec![];
+        for (_, values) in &self.names {
+            vectors.push(pool.len() as u32);
+            pool.extend((values.len() as u32).to_le_bytes());
+            for value in values {
+                pool.extend(value.to_le_bytes());
+            }
+        }
+        let mut table = vec![(0u32, 0u32); table_size];
+        let mask = table_size as u32 - 1;
+        for ((name, _), vector) in self.names.iter().zip(vectors) {
+            let hash = name_hash(name);
+            let step = (hash.wrapping_mul(17) & mask) | 1;
+            let mut slot = hash & mask;
+            while table[slot as usize] != (0, 0) {
+                slot = (slot + step) & mask;
+            }
+            table[slot as usize] = (pool.len() as u32, vector);
+            pool.extend(name.as_bytes());
+            pool.push(0);
+        }
+        for (name, vector) in table {
+            out.extend(name.to_le_bytes());
+            out.extend(vector.to_le_bytes());
+        }
+        out.extend(pool);
+        out
+    }
+}
+
+/// The hash of the names in the index, case insensitive since version 5.
+fn name_hash(name: &str) -> u32 {
+    name.bytes().fold(0u32, |hash, c| {
+        hash.wrapping_mul(67)
+            .wrapping_add(c.to_ascii_lowercase() as u32)
+            .wrapping_sub(113)
+    })
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
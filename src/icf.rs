@@ -0,0 +1,330 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    context::Context,
+    linker::{SectionFlag, SectionIndex, SectionType},
+    relocation::RelType,
+    section::SectionId,
+    symbol::ShareSymbol,
+    utils::{input_elf::InputElf, leb128::read_uleb128},
+};
+
+/// What a relocation refers to, with the symbol value and the addend
+/// folded into one offset.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Target {
+    Section(SectionId, i64),
+    Symbol(String, i64),
+}
+
+/// A relocation before the global symbols are resolved, which can only be
+/// done once the referring object is unlocked.
+enum RawTarget {
+    Resolved(Target),
+    Global(ShareSymbol, i64),
+}
+
+struct Candidate {
+    id: SectionId,
+    // flags and alignment
+    attrs: (u64, u64),
+    data: Vec<u8>,
+    // (offset, type) of each relocation
+    rels: Vec<(u64, u32)>,
+    raw_targets: Vec<RawTarget>,
+    targets: Vec<Target>,
+}
+
+impl Context {
+    /// Fold the read-only sections that have the same contents and whose
+    /// relocations refer to the same or to identical sections.
+    pub fn icf_sections(&mut self) {
+        let safe = match self.args.icf.as_deref() {
+            None | Some("none") => return,
+            Some("all") => false,
+            Some("safe") => true,
+            Some(mode) => panic!("unknown --icf mode: {mode}"),
+        };
+
+        let mut candidates = vec![];
+        let mut address_taken = vec![];
+        for obj in self.objects_in_order() {
+            let obj = obj.lock().unwrap();
+            if obj.is_dso {
+                continue;
+            }
+            obj.collect_icf_candidates(&mut candidates);
+            if safe {
+                obj.collect_address_significant(self, &mut address_taken);
+            }
+        }
+        for candidate in &mut candidates {
+            let raw_targets = std::mem::take(&mut candidate.raw_targets);
+            candidate.targets = raw_targets
+                .into_iter()
+                .map(|target| self.resolve_icf_target(target))
+                .collect();
+        }
+
+        let mut address_significant = HashSet::new();
+        for target in address_taken {
+            if let Target::Section(id, _) = self.resolve_icf_target(target) {
+                address_significant.insert(id);
+            }
+        }
+        candidates.retain(|c| !address_significant.contains(&c.id));
+
+        let classes = Self::partition_identical_sections(&candidates);
+        let mut leaders: HashMap<usize, SectionId> = HashMap::new();
+        let mut folded: Vec<(SectionId, SectionId)> = vec![];
+        for (candidate, class) in candidates.iter().zip(classes) {
+            match leaders.get(&class) {
+                Some(&leader) => folded.push((candidate.id, leader)),
+                None => {
+                    leaders.insert(class, candidate.id);
+                }
+            }
+        }
+
+        if self.args.print_icf_sections {
+            self.print_icf_sections(&folded);
+        }
+        for ((id, shndx), leader) in folded {
+            let obj = self.get_object(id).unwrap();
+            let mut obj = obj.lock().unwrap();
+            obj.section_info.sections[shndx]
+                .as_mut()
+                .unwrap()
+                .folded_into = Some(leader);
+        }
+    }
+
+    fn resolve_icf_target(&self, target: RawTarget) -> Target {
+        let (sym, addend) = match target {
+            RawTarget::Resolved(target) => return target,
+            RawTarget::Global(sym, addend) => (sym, addend),
+        };
+        let sym = sym.lock().unwrap();
+        let fallback = Target::Symbol(sym.name.clone(), addend);
+        let Some(id) = sym.elf.filter(|_| !sym.is_imported) else {
+            return fallback;
+        };
+        // e.g. a symbol defined by the linker
+        let Some(obj) = self.get_object(id) else {
+            return fallback;
+        };
+        let obj = obj.lock().unwrap();
+        let elf_sym = &obj.symbol_info.as_ref().unwrap().elf_symbols[sym.index];
+        match elf_sym.index() {
+            SectionIndex::Other(shndx) => {
+                Target::Section((id, shndx as usize), elf_sym.val as i64 + addend)
+            }
+            _ => fallback,
+        }
+    }
+
+    /// Assign an equivalence class to every candidate. The classes start
+    /// from the contents and are split on the classes of the relocation
+    /// targets until nothing changes.
+    fn partition_identical_sections(candidates: &[Candidate]) -> Vec<usize> {
+        let index: HashMap<SectionId, usize> = candidates
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (c.id, i))
+            .collect();
+        // a target in a candidate section is compared by class, the others
+        // must be the same
+        let fixed = |target: &Target| match target {
+            Target::Section(id, _) if index.contains_key(id) => None,
+            _ => Some(target.clone()),
+        };
+
+        let mut ids = HashMap::new();
+        let mut classes = candidates
+            .iter()
+            .map(|c| {
+                let key = (
+                    c.attrs,
+                    &c.data,
+                    &c.rels,
+                    c.targets.iter().map(fixed).collect::<Vec<_>>(),
+                );
+                let len = ids.len();
+                *ids.entry(key).or_insert(len)
+            })
+            .collect::<Vec<_>>();
+        let mut num_classes = ids.len();
+
+        loop {
+            let mut ids = HashMap::new();
+            let next = candidates
+                .iter()
+                .enumerate()
+                .map(|(i, c)| {
+                    let targets = c
+                        .targets
+                        .iter()
+                        .map(|target| match target {
+                            Target::Section(id, offset) => {
+                                index.get(id).map(|&j| (classes[j], *offset))
+                            }
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>();
+                    let len = ids.len();
+                    *ids.entry((classes[i], targets)).or_insert(len)
+                })
+                .collect::<Vec<_>>();
+            classes = next;
+            if ids.len() == num_classes {
+                return classes;
+            }
+            num_classes = ids.len();
+        }
+    }
+
+    fn print_icf_sections(&self, folded: &[(SectionId, SectionId)]) {
+        let name = |(id, shndx): SectionId| {
+            let obj = self.get_object(id).unwrap();
+            let obj = obj.lock().unwrap();
+            let sec = obj.section_info.sections[shndx].as_ref().unwrap();
+            format!("{}:({})", obj.name, sec.name)
+        };
+        let mut folded = folded.to_vec();
+        folded.sort_by_key(|&(_, leader)| leader);
+        let mut last = None;
+        for (id, leader) in folded {
+            if last != Some(leader) {
+                println!("selected section {}", name(leader));
+                last = Some(leader);
+            }
+            println!("  removing identical section {}", name(id));
+        }
+    }
+
+    /// Give the folded sections the place of the section they are folded
+    /// into, so that their symbols resolve there.
+    pub fn place_folded_sections(&mut self) {
+        for obj in self.objects_in_order() {
+            let folded = {
+                let obj = obj.lock().unwrap();
+                obj.section_info
+                    .sections
+                    .iter()
+                    .flatten()
+                    .filter_map(|sec| sec.folded_into.map(|leader| (sec.index, leader)))
+                    .collect::<Vec<_>>()
+            };
+            for (shndx, (id, leader_shndx)) in folded {
+                let (out_sec, offset) = {
+                    let leader = self.get_object(id).unwrap();
+                    let leader = leader.lock().unwrap();
+                    let sec = leader.section_info.sections[leader_shndx].as_ref().unwrap();
+                    (sec.out_sec, sec.offset)
+                };
+                let mut obj = obj.lock().unwrap();
+                let sec = obj.section_info.sections[shndx].as_mut().unwrap();
+                sec.out_sec = out_sec;
+                sec.offset = offset;
+            }
+        }
+    }
+}
+
+impl InputElf {
+    fn collect_icf_candidates(&self, out: &mut Vec<Candidate>) {
+        if self.symbol_info.is_none() {
+            return;
+        }
+        for (i, header) in self.section_info.elf_sections.iter().enumerate() {
+            let Some(ref sec) = self.section_info.sections[i] else {
+                continue;
+            };
+            let excluded = SectionFlag::WRITE as u64
+                | SectionFlag::TLS as u64
+                | SectionFlag::MERGE as u64
+                | SectionFlag::LINK_ORDER as u64
+                | SectionFlag::GNU_RETAIN as u64;
+            if header._type != SectionType::PROGBITS
+                || header.flags & SectionFlag::ALLOC as u64 == 0
+                || header.flags & excluded != 0
+                || sec.data.is_empty()
+                || sec.name == ".eh_frame"
+                || Self::is_kept_section(&sec.name)
+            {
+                continue;
+            }
+            let relocations = &self.section_info.relocations[i];
+            let raw_targets = relocations
+                .iter()
+                .map(|rel| self.icf_target(rel.sym(), rel.addend))
+                .collect();
+            out.push(Candidate {
+                id: (self.id, i),
+                attrs: (header.flags, header.add_align),
+                data: sec.data.clone(),
+                rels: relocations.iter().map(|r| (r.offset, r.typ())).collect(),
+                raw_targets,
+                targets: vec![],
+            });
+        }
+    }
+
+    fn icf_target(&self, sym: usize, addend: i64) -> RawTarget {
+        let info = self.symbol_info.as_ref().unwrap();
+        if sym >= info.first_global {
+            return RawTarget::Global(info.symbol(sym), addend);
+        }
+        let elf_sym = &info.elf_symbols[sym];
+        match elf_sym.index() {
+            SectionIndex::Other(shndx) => RawTarget::Resolved(Target::Section(
+                (self.id, shndx as usize),
+                elf_sym.val as i64 + addend,
+            )),
+            _ => RawTarget::Resolved(Target::Symbol(format!("{}:{}", self.name, sym), addend)),
+        }
+    }
+
+    /// The symbols whose address is significant, i.e. may be compared.
+    /// They are listed in .llvm_addrsig, without it any reference that
+    /// isn't a call takes the address.
+    fn collect_address_significant(&self, ctx: &Context, out: &mut Vec<RawTarget>) {
+        let Some(ref info) = self.symbol_info else {
+            return;
+        };
+        let addrsig = (0..self.section_info.elf_sections.len())
+            .find(|&i| self.section_info.elf_sections[i]._type == SectionType::LLVM_ADDRSIG);
+        if let Some(i) = addrsig {
+            let data = &self.section_info.sections[i].as_ref().unwrap().data;
+            let mut pos = 0;
+            while pos < data.len() {
+                let (sym, len) = read_uleb128(&data[pos..]);
+                pos += len;
+                out.push(self.icf_target(sym as usize, 0));
+            }
+        } else {
+            for (i, header) in self.section_info.elf_sections.iter().enumerate() {
+                // .eh_frame refers to every function
+                if header.flags & SectionFlag::ALLOC as u64 == 0
+                    || header._type == SectionType::X86_64_UNWIND
+                    || self.is_discarded(i)
+                    || self.section_info.sections[i].as_ref().unwrap().name == ".eh_frame"
+                {
+                    continue;
+                }
+                for rel in &self.section_info.relocations[i] {
+                    if RelType::from(rel.typ()) != RelType::R_X86_64_PLT32 {
+                        out.push(self.icf_target(rel.sym(), 0));
+                    }
+                }
+            }
+        }
+
+        // the users of a shared library may compare its functions
+        if ctx.args.shared {
+            for i in info.first_global..info.elf_symbols.len() {
+                out.push(self.icf_target(i, 0));
+            }
+        }
+    }
+}
@@ -0,0 +1,712 @@
+use std::{
+    collections::HashMap, fs, io::Cursor, os::unix::fs::PermissionsExt, rc::Rc, sync::Mutex,
+};
+
+use crate::{
+    context::Context,
+    linker::{
+        ElfHeader, ProgramHeader, SectionFlag, SectionHeader, SectionType, SegmentFlag, SegmentType,
+    },
+    output_section::{
+        output_section::{OutputSection, ShareOutputSection},
+        regular_section::RegularSection,
+    },
+    utils::{input_elf::InputElf, read_struct::write_struct, str_table::StrTableBuilder},
+};
+
+const PAGE_SIZE: u64 = 0x1000;
+const IMAGE_BASE: u64 = 0x400000;
+const EHDR_SIZE: u64 = size_of::<ElfHeader>() as u64;
+const PHDR_SIZE: u64 = size_of::<ProgramHeader>() as u64;
+const SHDR_SIZE: u64 = size_of::<SectionHeader>() as u64;
+
+/// The output section an input section goes into, e.g. .text.foo is
+/// merged into .text.
+fn output_section_name(name: &str) -> &str {
+    const PREFIXES: [&str; 18] = [
+        ".text",
+        ".data.rel.ro",
+        ".data",
+        ".rodata",
+        ".bss.rel.ro",
+        ".bss",
+        ".tdata",
+        ".tbss",
+        ".init_array",
+        ".fini_array",
+        ".ctors",
+        ".dtors",
+        ".gcc_except_table",
+        ".gnu.linkonce.t",
+        ".ltext",
+        ".ldata",
+        ".lrodata",
+        ".lbss",
+    ];
+    for prefix in PREFIXES {
+        if name == prefix
+            || name
+                .strip_prefix(prefix)
+                .is_some_and(|s| s.starts_with('.'))
+        {
+            return prefix;
+        }
+    }
+    name
+}
+
+/// The priority of the constructors and destructors without one, they
+/// come after the others.
+const DEFAULT_PRIORITY: u32 = 65536;
+
+/// The output section, its type and the priority of an input section of
+/// .init_array or .fini_array, or of .ctors or .dtors which are turned
+/// into them. The last field tells the latter, which run backwards: the
+/// priority of .ctors.N is 65535 - N.
+fn init_fini_section(name: &str) -> Option<(&'static str, SectionType, u32, bool)> {
+    use SectionType::*;
+    const TABLES: [(&str, &str, SectionType, bool); 4] = [
+        (".init_array", ".init_array", INIT_ARRAY, false),
+        (".fini_array", ".fini_array", FINI_ARRAY, false),
+        (".ctors", ".init_array", INIT_ARRAY, true),
+        (".dtors", ".fini_array", FINI_ARRAY, true),
+    ];
+    for (prefix, out, typ, backwards) in TABLES {
+        let Some(suffix) = name.strip_prefix(prefix) else {
+            continue;
+        };
+        if suffix.is_empty() {
+            return Some((out, typ, DEFAULT_PRIORITY, backwards));
+        }
+        if let Some(Ok(priority)) = suffix.strip_prefix('.').map(str::parse::<u32>) {
+            let priority = if backwards {
+                65535u32.saturating_sub(priority)
+            } else {
+                priority
+            };
+            return Some((out, typ, priority, backwards));
+        }
+    }
+    None
+}
+
+type OutputSectionMap = HashMap<(String, u32, u64), Rc<Mutex<RegularSection>>>;
+
+pub fn has_flag(header: &SectionHeader, flag: SectionFlag) -> bool {
+    header.flags & flag as u64 != 0
+}
+
+/// Writable sections which are only written during relocation, they go
+/// into PT_GNU_RELRO.
+fn is_relro(header: &SectionHeader, name: &str) -> bool {
+    use SectionType::*;
+    if !has_flag(header, SectionFlag::WRITE) || has_flag(header, SectionFlag::X86_64_LARGE) {
+        return false;
+    }
+    has_flag(header, SectionFlag::TLS)
+        || matches!(
+            header._type,
+            INIT_ARRAY | FINI_ARRAY | PREINIT_ARRAY | DYNAMIC
+        )
+        || [".data.rel.ro", ".bss.rel.ro", ".got", ".ctors", ".dtors"].contains(&name)
+}
+
+/// The order of the output sections: read-only data, code, TLS
+/// templates, relro data and bss, data, bss, the sections of the large
+/// code model and finally the non-alloc sections. The bss goes after all
+/// the data of the segment, where it takes no room in the file, but for
+/// the relro one which has to be next to the rest of PT_GNU_RELRO.
+fn section_rank(header: &SectionHeader, name: &str) -> u32 {
+    if !has_flag(header, SectionFlag::ALLOC) {
+        return 100;
+    }
+    let is_nobits = header._type == SectionType::NOBITS;
+    if has_flag(header, SectionFlag::X86_64_LARGE) {
+        // after everything else, so that they don't push the small
+        // model sections apart
+        return if !has_flag(header, SectionFlag::WRITE) {
+            60 + has_flag(header, SectionFlag::EXECINSTR) as u32
+        } else {
+            62 + is_nobits as u32
+        };
+    }
+    if name == ".interp" {
+        0
+    } else if header._type == SectionType::NOTE {
+        1
+    } else if !has_flag(header, SectionFlag::WRITE) {
+        if has_flag(header, SectionFlag::EXECINSTR) {
+            20
+        } else {
+            10
+        }
+    } else if has_flag(header, SectionFlag::TLS) {
+        30 + is_nobits as u32
+    } else if is_relro(header, name) {
+        40 + is_nobits as u32
+    } else if is_nobits {
+        52
+    } else {
+        50
+    }
+}
+
+/// The large sections get their own segments even if the permissions
+/// match those before them.
+fn segment_key(header: &SectionHeader) -> (u32, bool) {
+    (
+        segment_flags(header),
+        has_flag(header, SectionFlag::X86_64_LARGE),
+    )
+}
+
+fn segment_flags(header: &SectionHeader) -> u32 {
+    let mut flags = SegmentFlag::R as u32;
+    if has_flag(header, SectionFlag::WRITE) {
+        flags |= SegmentFlag::W as u32;
+    }
+    if has_flag(header, SectionFlag::EXECINSTR) {
+        flags |= SegmentFlag::X as u32;
+    }
+    flags
+}
+
+/// A segment covering exactly the section of `header`.
+fn segment_of(typ: SegmentType, flags: u32, header: &SectionHeader) -> ProgramHeader {
+    ProgramHeader {
+        _type: typ as u32,
+        flags,
+        offset: header.offset,
+        vaddr: header.addr,
+        paddr: header.addr,
+        file_size: header.size,
+        mem_size: header.size,
+        align: header.add_align,
+    }
+}
+
+/// .tbss only exists in the TLS template, it takes no room in the image.
+pub fn is_tbss(header: &SectionHeader) -> bool {
+    has_flag(header, SectionFlag::TLS) && header._type == SectionType::NOBITS
+}
+
+impl Context {
+    /// Put the input sections of the live objects into output sections.
+    /// Mergeable sections are already in their `MergedSection`.
+    pub fn create_output_sections(&mut self) {
+        let mut map = OutputSectionMap::new();
+        // (priority, object, section index) of the constructors and
+        // destructors, placed after the rest once sorted
+        let mut init_fini = vec![];
+        for obj in self.objects_in_order() {
+            let mut obj = obj.lock().unwrap();
+            if !obj.is_alive || obj.is_dso {
+                continue;
+            }
+            let obj_id = obj.id;
+            for i in 0..obj.section_info.elf_sections.len() {
+                let header = obj.section_info.elf_sections[i].clone();
+                let Some(ref sec) = obj.section_info.sections[i] else {
+                    continue;
+                };
+                // already in their MergedSection, where only the pieces of
+                // the kept sections are output
+                if let Some(ref merged) = obj.section_info.mergeable_sections[i] {
+                    for frag in &merged.fragments {
+                        frag.lock().unwrap().is_alive = true;
+                    }
+                    continue;
+                }
+                if matches!(
+                    header._type,
+                    SectionType::GROUP | SectionType::SYMTAB_SHNDX | SectionType::LLVM_ADDRSIG
+                ) || has_flag(&header, SectionFlag::EXCLUDE)
+                    || sec.folded_into.is_some()
+                    || obj.eh_frame.as_ref().is_some_and(|frame| frame.shndx == i)
+                    || sec.name == ".note.GNU-stack"
+                    || (self.args.gdb_index && sec.name.starts_with(".debug_gnu_pub"))
+                    || (self.args.strips_debug() && sec.name.starts_with(".debug"))
+                {
+                    continue;
+                }
+                if let Some((_, _, priority, _)) = init_fini_section(&sec.name) {
+                    init_fini.push((priority, obj_id, i));
+                    continue;
+                }
+                let name = output_section_name(&sec.name).to_string();
+                self.place_input_section(&mut map, &mut obj, i, &name, header._type);
+            }
+        }
+        init_fini.sort_by_key(|&(priority, _, _)| priority);
+        for (_, obj_id, i) in init_fini {
+            let obj = self.get_object(obj_id).unwrap();
+            let mut obj = obj.lock().unwrap();
+            let name = &obj.section_info.sections[i].as_ref().unwrap().name;
+            let (name, typ, _, backwards) = init_fini_section(name).unwrap();
+            if backwards {
+                obj.reverse_ctors(i);
+            }
+            self.place_input_section(&mut map, &mut obj, i, name, typ);
+        }
+        self.merge_copyrel_sections(&map);
+        self.define_init_fini_symbols();
+        self.create_eh_frame_section();
+        self.create_gdb_index_section();
+        self.create_symtab_sections();
+        self.place_folded_sections();
+    }
+
+    /// Append the input section `i` of `obj` to the output section `name`,
+    /// created on first use.
+    fn place_input_section(
+        &mut self,
+        map: &mut OutputSectionMap,
+        obj: &mut InputElf,
+        i: usize,
+        name: &str,
+        typ: SectionType,
+    ) {
+        let ignored_flags = SectionFlag::GROUP as u64
+            | SectionFlag::MERGE as u64
+            | SectionFlag::STRINGS as u64
+            | SectionFlag::COMPRESSED as u64;
+        let header = &obj.section_info.elf_sections[i];
+        let (size, align) = (header.size, header.add_align);
+        let flags = header.flags & !ignored_flags;
+        let key = (name.to_string(), typ as u32, flags);
+        let out_sec = match map.get(&key) {
+            Some(out_sec) => out_sec.clone(),
+            None => {
+                let out_sec =
+                    self.add_output_section(|id| RegularSection::new(id, name, typ, flags));
+                map.insert(key, out_sec.clone());
+                out_sec
+            }
+        };
+        let mut out_sec = out_sec.lock().unwrap();
+        let offset = out_sec.add(obj.id, i, size, align);
+        let sec = obj.section_info.sections[i].as_mut().unwrap();
+        sec.out_sec = Some(out_sec.id());
+        sec.offset = offset;
+    }
+
+    /// Put the space of the copy relocations at the end of the .bss or
+    /// .data.rel.ro made of input sections, rather than in a second
+    /// output section of the same name.
+    fn merge_copyrel_sections(&mut self, map: &OutputSectionMap) {
+        for copyrel in [&self.copyrel, &self.copyrel_relro].into_iter().flatten() {
+            let mut copyrel = copyrel.lock().unwrap();
+            let header = copyrel.section_header().clone();
+            let key = (copyrel.name(), header._type as u32, header.flags);
+            let Some(out_sec) = map.get(&key) else {
+                continue;
+            };
+            let mut out_sec = out_sec.lock().unwrap();
+            let offset = out_sec.reserve(header.size, header.add_align.max(1));
+            copyrel.merged_into = Some((out_sec.id(), offset));
+            self.sections.remove(&copyrel.id());
+        }
+    }
+
+    /// Point __init_array_start and the like to the boundaries of their
+    /// sections, the startup code of libc calls the functions between
+    /// them. Without such a section, both are the end of .text, an empty
+    /// array, since crt1.o refers to them anyway.
+    fn define_init_fini_symbols(&mut self) {
+        let find = |name: &str| {
+            self.sections.iter().find_map(|(&id, sec)| {
+                let sec = sec.lock().unwrap();
+                (sec.name() == name).then(|| (id, sec.section_header().size))
+            })
+        };
+        let text = find(".text");
+        for name in ["preinit_array", "init_array", "fini_array"] {
+            let Some((id, start, end)) = find(&format!(".{}", name))
+                .map(|(id, size)| (id, 0, size))
+                .or(text.map(|(id, size)| (id, size, size)))
+            else {
+                continue;
+            };
+            self.define_synthetic_symbol(&format!("__{}_start", name), id, start);
+            self.define_synthetic_symbol(&format!("__{}_end", name), id, end);
+        }
+    }
+
+    /// Sort the output sections, assign their addresses and file offsets,
+    /// and create the program headers.
+    pub fn layout(&mut self) {
+        for sec in self.sections.values() {
+            if let Some(merged) = sec.lock().unwrap().to_mergeable() {
+                merged.assign_offsets();
+            }
+        }
+        if let Some(ref dynamic) = self.dynamic {
+            dynamic.lock().unwrap().update_size(self);
+        }
+
+        let mut order = self
+            .sections
+            .iter()
+            .map(|(&id, sec)| {
+                let sec = sec.lock().unwrap();
+                (section_rank(sec.section_header(), &sec.name()), id)
+            })
+            .collect::<Vec<_>>();
+        order.sort();
+        self.section_order = order.into_iter().map(|(_, id)| id).collect();
+        let sections = self.output_sections();
+        for (i, sec) in sections.iter().enumerate() {
+            sec.lock().unwrap().wrapper_mut().shndx = i + 1;
+        }
+        self.set_section_links(&sections);
+
+        let alloc = sections
+            .iter()
+            .filter(|sec| has_flag(sec.lock().unwrap().section_header(), SectionFlag::ALLOC))
+            .collect::<Vec<_>>();
+        let tls_align = alloc
+            .iter()
+            .map(|sec| sec.lock().unwrap().section_header().clone())
+            .filter(|header| has_flag(header, SectionFlag::TLS))
+            .map(|header| header.add_align.max(1))
+            .max();
+
+        // the first segment starts with the ELF and program headers,
+        // a new one starts on a new page whenever the permissions change
+        let mut num_loads = 1;
+        let mut key = (SegmentFlag::R as u32, false);
+        for sec in &alloc {
+            let sec_key = segment_key(sec.lock().unwrap().section_header());
+            if sec_key != key {
+                num_loads += 1;
+                key = sec_key;
+            }
+        }
+        let has_relro = alloc.iter().any(|sec| {
+            let sec = sec.lock().unwrap();
+            is_relro(sec.section_header(), &sec.name())
+        });
+        // PT_PHDR and PT_INTERP, PT_TLS, PT_DYNAMIC, PT_GNU_EH_FRAME,
+        // PT_GNU_STACK and PT_GNU_RELRO
+        let phnum = num_loads
+            + 2 * self.interp.is_some() as u64
+            + tls_align.is_some() as u64
+            + self.dynamic.is_some() as u64
+            + self.eh_frame_hdr.is_some() as u64
+            + 1
+            + has_relro as u64;
+
+        let base = if self.is_pic() { 0 } else { IMAGE_BASE };
+        let headers_size = EHDR_SIZE + PHDR_SIZE * phnum;
+        let mut loads = vec![ProgramHeader {
+            _type: SegmentType::LOAD as u32,
+            flags: SegmentFlag::R as u32,
+            offset: 0,
+            vaddr: base,
+            paddr: base,
+            file_size: headers_size,
+            mem_size: headers_size,
+            align: PAGE_SIZE,
+        }];
+        let mut tls: Option<ProgramHeader> = None;
+        let mut relro: Option<ProgramHeader> = None;
+        let mut addr = base + headers_size;
+        let mut key = (SegmentFlag::R as u32, false);
+        for sec in &alloc {
+            let mut sec = sec.lock().unwrap();
+            let header = sec.section_header().clone();
+            let sec_key = segment_key(&header);
+            let sec_relro = is_relro(&header, &sec.name());
+            if !sec_relro && relro.is_some() && sec_key == key {
+                // the dynamic loader makes the pages of PT_GNU_RELRO
+                // read-only, what follows starts on the next page
+                addr = addr.next_multiple_of(PAGE_SIZE);
+            }
+            if sec_key != key {
+                key = sec_key;
+                addr = addr.next_multiple_of(PAGE_SIZE);
+                // the bss before takes no room in the file
+                let last = loads.last().unwrap();
+                let offset = (last.offset + last.file_size).next_multiple_of(PAGE_SIZE);
+                loads.push(ProgramHeader {
+                    _type: SegmentType::LOAD as u32,
+                    flags: key.0,
+                    offset,
+                    vaddr: addr,
+                    paddr: addr,
+                    align: PAGE_SIZE,
+                    ..Default::default()
+                });
+            }
+            let mut align = header.add_align.max(1);
+            if has_flag(&header, SectionFlag::TLS) && tls.is_none() {
+                // the TLS template is aligned as a whole
+                align = tls_align.unwrap();
+                let load = loads.last().unwrap();
+                tls = Some(ProgramHeader {
+                    _type: SegmentType::TLS as u32,
+                    flags: SegmentFlag::R as u32,
+                    offset: load.offset + addr.next_multiple_of(align) - load.vaddr,
+                    vaddr: addr.next_multiple_of(align),
+                    paddr: addr.next_multiple_of(align),
+                    align,
+                    ..Default::default()
+                });
+            }
+            addr = addr.next_multiple_of(align);
+            let load = loads.last_mut().unwrap();
+            let header = &mut sec.wrapper_mut().elf_header;
+            header.addr = addr;
+            header.offset = load.offset + addr - load.vaddr;
+            let end = addr + header.size;
+
+            if has_flag(header, SectionFlag::TLS) {
+                let tls = tls.as_mut().unwrap();
+                tls.mem_size = end - tls.vaddr;
+                if header._type != SectionType::NOBITS {
+                    tls.file_size = end - tls.vaddr;
+                }
+            }
+            if !is_tbss(header) {
+                if header._type != SectionType::NOBITS {
+                    load.file_size = end - load.vaddr;
+                }
+                load.mem_size = end - load.vaddr;
+                addr = end;
+            }
+            if sec_relro {
+                let relro = relro.get_or_insert_with(|| ProgramHeader {
+                    _type: SegmentType::GNU_RELRO as u32,
+                    flags: SegmentFlag::R as u32,
+                    offset: header.offset,
+                    vaddr: header.addr,
+                    paddr: header.addr,
+                    align: 1,
+                    ..Default::default()
+                });
+                relro.file_size = addr - relro.vaddr;
+                relro.mem_size = addr - relro.vaddr;
+            }
+        }
+
+        let last = loads.last().unwrap();
+        self.place_non_alloc_sections(last.offset + last.file_size);
+
+        self.section_addrs = sections
+            .iter()
+            .zip(&self.section_order)
+            .map(|(sec, &id)| (id, sec.lock().unwrap().section_header().addr))
+            .collect();
+        self.phdrs = vec![];
+        if let Some(ref interp) = self.interp {
+            // the dynamic loader finds the program headers through PT_PHDR,
+            // which comes before the segments
+            let size = PHDR_SIZE * phnum;
+            self.phdrs.push(ProgramHeader {
+                _type: SegmentType::PHDR as u32,
+                flags: SegmentFlag::R as u32,
+                offset: EHDR_SIZE,
+                vaddr: base + EHDR_SIZE,
+                paddr: base + EHDR_SIZE,
+                file_size: size,
+                mem_size: size,
+                align: 8,
+            });
+            let header = interp.lock().unwrap().section_header().clone();
+            self.phdrs.push(segment_of(
+                SegmentType::INTERP,
+                SegmentFlag::R as u32,
+                &header,
+            ));
+        }
+        self.phdrs.extend(loads);
+        if let Some(tls) = tls {
+            self.tls_begin = tls.vaddr;
+            self.tls_end = tls.vaddr + tls.mem_size.next_multiple_of(tls.align);
+            self.phdrs.push(tls);
+        }
+        if let Some(ref dynamic) = self.dynamic {
+            let header = dynamic.lock().unwrap().section_header().clone();
+            let flags = SegmentFlag::R as u32 | SegmentFlag::W as u32;
+            self.phdrs
+                .push(segment_of(SegmentType::DYNAMIC, flags, &header));
+        }
+        if let Some(ref hdr) = self.eh_frame_hdr {
+            let header = hdr.lock().unwrap().section_header().clone();
+            self.phdrs.push(segment_of(
+                SegmentType::GNU_EH_FRAME,
+                SegmentFlag::R as u32,
+                &header,
+            ));
+        }
+        self.phdrs.push(ProgramHeader {
+            _type: SegmentType::GNU_STACK as u32,
+            flags: SegmentFlag::R as u32 | SegmentFlag::W as u32,
+            align: 16,
+            ..Default::default()
+        });
+        self.phdrs.extend(relro);
+        assert_eq!(self.phdrs.len() as u64, phnum);
+    }
+
+    /// Place the non-alloc sections again once their size changed, after
+    /// the layout.
+    pub fn update_non_alloc_offsets(&self) {
+        let last = self
+            .phdrs
+            .iter()
+            .rfind(|phdr| phdr._type == SegmentType::LOAD as u32)
+            .unwrap();
+        self.place_non_alloc_sections(last.offset + last.file_size);
+    }
+
+    /// The non-alloc sections follow the loaded part of the file, from
+    /// `offset`.
+    fn place_non_alloc_sections(&self, mut offset: u64) {
+        for id in &self.section_order {
+            let mut sec = self.sections[id].lock().unwrap();
+            let header = &mut sec.wrapper_mut().elf_header;
+            if has_flag(header, SectionFlag::ALLOC) {
+                continue;
+            }
+            offset = offset.next_multiple_of(header.add_align.max(1));
+            header.offset = offset;
+            if header._type != SectionType::NOBITS {
+                offset += header.size;
+            }
+        }
+    }
+
+    /// The output sections in file order.
+    pub fn output_sections(&self) -> Vec<ShareOutputSection> {
+        self.section_order
+            .iter()
+            .map(|id| self.sections[id].clone())
+            .collect()
+    }
+
+    fn set_section_links(&self, sections: &[ShareOutputSection]) {
+        let shndx = |name: &str| {
+            sections
+                .iter()
+                .position(|sec| sec.lock().unwrap().name() == name)
+                .map_or(0, |i| i as u32 + 1)
+        };
+        let (dynsym, dynstr) = (shndx(".dynsym"), shndx(".dynstr"));
+        let got_plt = shndx(".got.plt");
+        let strtab = shndx(".strtab");
+        for sec in sections {
+            let mut sec = sec.lock().unwrap();
+            let name = sec.name();
+            let header = &mut sec.wrapper_mut().elf_header;
+            match name.as_str() {
+                ".dynsym" | ".gnu.version_r" | ".gnu.version_d" | ".dynamic" => {
+                    header.link = dynstr
+                }
+                ".gnu.version" | ".rela.dyn" | ".hash" => header.link = dynsym,
+                ".symtab" => header.link = strtab,
+                ".rela.plt" => {
+                    header.link = dynsym;
+                    header.info = got_plt;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Write the ELF header, the program headers, the sections and the
+    /// section headers into the output file.
+    pub fn write_output(&self) {
+        let sections = self.output_sections();
+        let mut shstrtab = StrTableBuilder::new();
+        let mut shdrs = vec![SectionHeader::default()];
+        for sec in &sections {
+            let sec = sec.lock().unwrap();
+            let mut header = sec.section_header().clone();
+            header.name = shstrtab.add(&sec.name());
+            shdrs.push(header);
+        }
+        let shstrtab_name = shstrtab.add(".shstrtab");
+        let shstrtab_offset = shdrs
+            .iter()
+            .filter(|h| h._type != SectionType::NOBITS)
+            .map(|h| h.offset + h.size)
+            .max()
+            .unwrap();
+        let shstrtab_data = shstrtab.data();
+        shdrs.push(SectionHeader {
+            name: shstrtab_name,
+            _type: SectionType::STRTAB,
+            offset: shstrtab_offset,
+            size: shstrtab_data.len() as u64,
+            add_align: 1,
+            ..Default::default()
+        });
+        let sh_off = (shstrtab_offset + shstrtab_data.len() as u64).next_multiple_of(8);
+        let file_size = sh_off + SHDR_SIZE * shdrs.len() as u64;
+        let mut buf = vec![0u8; file_size as usize];
+
+        let entry = self
+            .get_symbol("_start")
+            .map_or(0, |sym| self.symbol_addr(&sym.lock().unwrap()));
+        let mut ident = [0u8; 16];
+        // ELFCLASS64, little endian, the current version, System V ABI
+        ident[..7].copy_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1]);
+        let elf_header = ElfHeader {
+            ident,
+            // ET_DYN or ET_EXEC
+            _type: if self.is_pic() { 3 } else { 2 },
+            // EM_X86_64
+            machine: 62,
+            version: 1,
+            entry,
+            ph_off: EHDR_SIZE,
+            sh_off,
+            flags: 0,
+            eh_size: EHDR_SIZE as u16,
+            ph_ent_size: PHDR_SIZE as u16,
+            ph_num: self.phdrs.len() as u16,
+            sh_ent_size: SHDR_SIZE as u16,
+            sh_num: shdrs.len() as u16,
+            sh_strndx: (shdrs.len() - 1) as u16,
+        };
+        let mut cursor = Cursor::new(&mut buf[..]);
+        write_struct(&mut cursor, &elf_header).unwrap();
+        for phdr in &self.phdrs {
+            write_struct(&mut cursor, phdr).unwrap();
+        }
+
+        for sec in &sections {
+            let sec = sec.lock().unwrap();
+            let header = sec.section_header();
+            if header._type == SectionType::NOBITS {
+                continue;
+            }
+            let (offset, size) = (header.offset as usize, header.size as usize);
+            sec.copy_buf(self, &mut buf[offset..offset + size]);
+        }
+        let offset = shstrtab_offset as usize;
+        buf[offset..offset + shstrtab_data.len()].copy_from_slice(&shstrtab_data);
+        let mut cursor = Cursor::new(&mut buf[sh_off as usize..]);
+        for shdr in &shdrs {
+            write_struct(&mut cursor, shdr).unwrap();
+        }
+
+        let output = &self.args.output;
+        fs::write(output, &buf).unwrap_or_else(|_| panic!("cannot write {}", output));
+        fs::set_permissions(output, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+}
+
+impl InputElf {
+    /// .ctors runs from the end to the start, unlike .init_array: reverse
+    /// the order of its pointers and of their relocations.
+    fn reverse_ctors(&mut self, shndx: usize) {
+        let sec = self.section_info.sections[shndx].as_mut().unwrap();
+        let size = sec.data.len() as u64;
+        sec.data = sec.data.chunks(8).rev().flatten().copied().collect();
+        for rela in &mut self.section_info.relocations[shndx] {
+            rela.offset = size - 8 - rela.offset;
+        }
+    }
+}
@@ -50,6 +50,89 @@ pub struct ElfSymbol {
     pub size: u64,
 }
 
+#[repr(C)]
+#[derive(Debug, Clone)]
+pub struct Rela {
+    pub offset: u64,
+    pub info: u64,
+    pub addend: i64,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Default)]
+pub struct ProgramHeader {
+    pub _type: u32,
+    pub flags: u32,
+    pub offset: u64,
+    pub vaddr: u64,
+    pub paddr: u64,
+    pub file_size: u64,
+    pub mem_size: u64,
+    pub align: u64,
+}
+
+// the header of a SHF_COMPRESSED section
+#[repr(C)]
+#[derive(Debug, Clone, Default)]
+pub struct CompressionHeader {
+    pub _type: u32,
+    pub reserved: u32,
+    // of the uncompressed data
+    pub size: u64,
+    pub add_align: u64,
+}
+
+pub const ELFCOMPRESS_ZLIB: u32 = 1;
+pub const ELFCOMPRESS_ZSTD: u32 = 2;
+
+#[repr(C)]
+#[derive(Debug, Clone)]
+pub struct DynamicEntry {
+    pub tag: i64,
+    pub val: u64,
+}
+
+// the version definition (.gnu.version_d) entry
+#[repr(C)]
+#[derive(Debug, Clone, Default)]
+pub struct Verdef {
+    pub version: u16,
+    pub flags: u16,
+    pub ndx: u16,
+    pub cnt: u16,
+    pub hash: u32,
+    pub aux: u32,
+    pub next: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Default)]
+pub struct Verdaux {
+    pub name: u32,
+    pub next: u32,
+}
+
+// the version requirement (.gnu.version_r) entry
+#[repr(C)]
+#[derive(Debug, Clone, Default)]
+pub struct Verneed {
+    pub version: u16,
+    pub cnt: u16,
+    pub file: u32,
+    pub aux: u32,
+    pub next: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Default)]
+pub struct Vernaux {
+    pub hash: u32,
+    pub flags: u16,
+    pub other: u16,
+    pub name: u32,
+    pub next: u32,
+}
+
 #[repr(u32)]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[allow(non_camel_case_types, unused)]
@@ -57,6 +140,12 @@ pub enum SectionType {
     DYNAMIC = 0x6,
     DYNSYM = 0xb,
     FINI_ARRAY = 0xf,
+    #[allow(clippy::upper_case_acronyms)]
+    GROUP = 0x11,
+    GNU_HASH = 0x6ffffff6,
+    GNU_VERDEF = 0x6ffffffd,
+    GNU_VERNEED = 0x6ffffffe,
+    GNU_VERSYM = 0x6fffffff,
     HASH = 0x5,
     HIPROC = 0x7fffffff,
     HIUSER = 0xffffffff,
@@ -73,6 +162,9 @@ pub enum SectionType {
     SHLIB = 0xa,
     STRTAB = 0x3,
     SYMTAB = 0x2,
+    SYMTAB_SHNDX = 0x12,
+    X86_64_UNWIND = 0x70000001,
+    LLVM_ADDRSIG = 0x6fff4c03,
 }
 impl Default for SectionType {
     fn default() -> Self {
@@ -95,11 +187,37 @@ pub enum SectionFlag {
     TLS = (1 << 10),             /* Section hold thread-local data.  */
     COMPRESSED = (1 << 11),      /* Section with compressed data. */
     MASKOS = 0x0ff00000,         /* OS-specific.  */
+    GNU_RETAIN = (1 << 21),      /* Not to be GCed by the linker.  */
+    X86_64_LARGE = 0x10000000,   /* Section far from the code and data of the small model */
     MASKPROC = 0xf0000000,       /* Processor-specific */
     ORDERED = (1 << 30),         /* Special ordering requirement (Solaris).  */
     EXCLUDE = (1 << 31),         /* Section is excluded unless referenced or allocated (Solaris).*/
 }
 
+#[repr(u32)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[allow(non_camel_case_types, unused, clippy::upper_case_acronyms)]
+pub enum SegmentType {
+    NULL = 0,
+    LOAD = 1,
+    DYNAMIC = 2,
+    INTERP = 3,
+    NOTE = 4,
+    PHDR = 6,
+    TLS = 7,
+    GNU_EH_FRAME = 0x6474e550,
+    GNU_STACK = 0x6474e551,
+    GNU_RELRO = 0x6474e552,
+}
+
+#[repr(u32)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SegmentFlag {
+    X = 1,
+    W = 2,
+    R = 4,
+}
+
 #[repr(u16)]
 #[derive(PartialEq, Eq, Clone)]
 #[allow(non_camel_case_types)]
@@ -119,6 +237,9 @@ pub enum SymbolBinding {
     LOCAL = 0,
     GLOBAL = 1,
     WEAK = 2,
+    // a global that stays unique in the process, e.g. a static local of a
+    // C++ inline function
+    GNU_UNIQUE = 10,
     LOPROC = 13,
     HIPROC = 15,
 }
@@ -132,10 +253,56 @@ pub enum SymbolType {
     FUNC = 2,
     SECTION = 3,
     FILE = 4,
+    #[allow(clippy::upper_case_acronyms)]
+    TLS = 6,
+    GNU_IFUNC = 10,
     LOPROC = 13,
     HIPROC = 15,
 }
 
+#[allow(unused)]
+pub mod version {
+    pub const VER_NDX_LOCAL: u16 = 0;
+    pub const VER_NDX_GLOBAL: u16 = 1;
+    pub const VER_NDX_HIDDEN: u16 = 0x8000;
+    pub const VER_FLG_BASE: u16 = 1;
+}
+
+// the tags of the .dynamic entries
+#[allow(unused)]
+pub mod dynamic_tag {
+    pub const DT_NULL: i64 = 0;
+    pub const DT_NEEDED: i64 = 1;
+    pub const DT_PLTRELSZ: i64 = 2;
+    pub const DT_PLTGOT: i64 = 3;
+    pub const DT_HASH: i64 = 4;
+    pub const DT_STRTAB: i64 = 5;
+    pub const DT_SYMTAB: i64 = 6;
+    pub const DT_RELA: i64 = 7;
+    pub const DT_RELASZ: i64 = 8;
+    pub const DT_RELAENT: i64 = 9;
+    pub const DT_STRSZ: i64 = 10;
+    pub const DT_SYMENT: i64 = 11;
+    pub const DT_INIT: i64 = 12;
+    pub const DT_FINI: i64 = 13;
+    pub const DT_SONAME: i64 = 14;
+    pub const DT_PLTREL: i64 = 20;
+    pub const DT_DEBUG: i64 = 21;
+    pub const DT_JMPREL: i64 = 23;
+    pub const DT_INIT_ARRAY: i64 = 25;
+    pub const DT_FINI_ARRAY: i64 = 26;
+    pub const DT_INIT_ARRAYSZ: i64 = 27;
+    pub const DT_FINI_ARRAYSZ: i64 = 28;
+    pub const DT_PREINIT_ARRAY: i64 = 32;
+    pub const DT_PREINIT_ARRAYSZ: i64 = 33;
+    pub const DT_RELACOUNT: i64 = 0x6ffffff9;
+    pub const DT_VERSYM: i64 = 0x6ffffff0;
+    pub const DT_VERDEF: i64 = 0x6ffffffc;
+    pub const DT_VERDEFNUM: i64 = 0x6ffffffd;
+    pub const DT_VERNEED: i64 = 0x6ffffffe;
+    pub const DT_VERNEEDNUM: i64 = 0x6fffffff;
+}
+
 impl ElfHeader {
     pub fn parse_ident(&self) -> Ident {
         Ident::new(self.ident.clone())
@@ -183,6 +350,37 @@ impl ElfSymbol {
     }
 }
 
+impl Rela {
+    pub fn new(offset: u64, sym: usize, typ: u32, addend: i64) -> Self {
+        Self {
+            offset,
+            info: ((sym as u64) << 32) | typ as u64,
+            addend,
+        }
+    }
+    pub fn sym(&self) -> usize {
+        (self.info >> 32) as usize
+    }
+    pub fn typ(&self) -> u32 {
+        self.info as u32
+    }
+}
+
+/// The hash function used by the SysV hash table and the symbol
+/// versioning sections.
+pub fn elf_hash(name: &str) -> u32 {
+    let mut h: u32 = 0;
+    for c in name.bytes() {
+        h = (h << 4).wrapping_add(c as u32);
+        let g = h & 0xf0000000;
+        if g != 0 {
+            h ^= g >> 24;
+        }
+        h &= !g;
+    }
+    h
+}
+
 impl From<u16> for SectionIndex {
     fn from(value: u16) -> Self {
         match value {
@@ -205,6 +403,8 @@ impl From<u8> for SymbolType {
             2 => Self::FUNC,
             3 => Self::SECTION,
             4 => Self::FILE,
+            6 => Self::TLS,
+            10 => Self::GNU_IFUNC,
             13 => Self::LOPROC,
             15 => Self::HIPROC,
             _ => panic!("cannot parse symbol type {}", value),
@@ -217,6 +417,7 @@ impl From<u8> for SymbolBinding {
             0 => Self::LOCAL,
             1 => Self::GLOBAL,
             2 => Self::WEAK,
+            10 => Self::GNU_UNIQUE,
             13 => Self::LOPROC,
             15 => Self::HIPROC,
             _ => panic!("cannot parse symbol binding {}", value),
@@ -1,59 +1,93 @@
 mod archive_parser;
 mod argument_parser;
+mod compress_debug;
 mod context;
+mod dynamic;
 mod e_header;
+mod eh_frame;
+mod gc_sections;
+mod gdb_index;
+mod icf;
+mod layout;
 mod linker;
 mod output_section;
 mod passes;
+mod relocation;
 mod section;
 mod symbol;
+mod symtab;
+mod synthetic_symbols;
 mod utils;
-use std::{fs::File, rc::Rc, str::from_utf8, sync::Mutex};
+mod version_script;
+use std::{env, rc::Rc, str::from_utf8, sync::Mutex};
 
-use argument_parser::Args;
-use clap::Parser;
+use argument_parser::{Args, Input};
+use clap::{CommandFactory, FromArgMatches};
 use context::Context;
 use linker::SectionFlag;
 use section::Section;
-use utils::input_elf::InputElf;
 
 pub type Id = Rc<Mutex<usize>>;
 
 fn main() {
-    let args = Args::parse();
+    let matches = Args::command().get_matches_from(Args::normalize(env::args()));
+    let args = Args::from_arg_matches(&matches).unwrap();
     // let args: Vec<String> = args().collect();
     // dbg!(&args);
-    let archive_parser = archive_parser::Parser::new(args.library_path);
+    let inputs = args.inputs(&matches);
+    let is_static = args.is_static();
+    let archive_parser = archive_parser::Parser::new(args.library_path.clone());
 
-    let mut ctx = Context::new();
+    let mut ctx = Context::new(args);
 
-    if let Some(library) = args.library {
-        for archive in library {
-            let elf = archive_parser.parse(archive);
-            for obj in elf {
-                ctx.push(obj);
+    let mut as_needed = false;
+    let mut state_stack = vec![];
+    for input in inputs {
+        let elfs = match input {
+            Input::AsNeeded(v) => {
+                as_needed = v;
+                continue;
             }
+            Input::PushState => {
+                state_stack.push(as_needed);
+                continue;
+            }
+            Input::PopState => {
+                as_needed = state_stack.pop().expect("--pop-state without --push-state");
+                continue;
+            }
+            Input::Library(library) => archive_parser.parse(library, is_static, as_needed),
+            Input::Object(obj_path) => archive_parser.open(obj_path, is_static, as_needed),
+        };
+        for elf in elfs {
+            if elf.name == "a.o" {
+                println!("name: {} \n {elf}", elf.name);
+            }
+            ctx.push(elf);
         }
     }
 
-    for obj_path in args.objects {
-        let f = File::open(&obj_path).expect(&format!("cannot open file {:?}", &obj_path));
-        let mut elf = InputElf::new(
-            f,
-            obj_path.file_name().unwrap().to_str().unwrap().to_string(),
-        );
-        elf.is_alive = true;
-        if elf.name == "a.o" {
-            println!("name: {} \n {elf}", elf.name);
-        }
-        ctx.push(elf);
-    }
-
     let elf_size = ctx.obj_size();
     dbg!(elf_size);
 
     ctx.resolve_symbol();
 
+    ctx.parse_eh_frames();
+    ctx.export_symbols();
+    ctx.gc_sections();
+    ctx.icf_sections();
+
+    ctx.reserve_synthetic_symbols();
+    ctx.scan_relocations();
+    ctx.create_dynamic_symbols();
+    ctx.create_output_sections();
+    ctx.layout();
+    ctx.define_synthetic_symbols();
+    ctx.write_gdb_index();
+    ctx.write_symtab();
+    ctx.compress_debug_sections();
+    ctx.write_output();
+
     for elf in ctx.object_iter() {
         let elf = elf.lock().unwrap();
         if elf.name != "a.o" {
@@ -0,0 +1,80 @@
+use crate::{
+    context::Context,
+    linker::{SectionFlag, SectionHeader, SectionType},
+    symbol::ShareSymbol,
+};
+
+use super::output_section::{OutputSection, SectionWrapper};
+
+/// The space reserved in the executable for the data objects of shared
+/// libraries, which the dynamic loader fills through R_X86_64_COPY.
+/// Read-only objects go into .data.rel.ro, the others into .bss. The
+/// space is appended to the output section of the same name if there is
+/// one, see `merge_copyrel_sections`.
+#[derive(Debug)]
+pub struct CopyrelSection {
+    section: SectionWrapper,
+    pub symbols: Vec<ShareSymbol>,
+    // (output section id, offset) of the space once it's merged
+    pub merged_into: Option<(usize, u64)>,
+}
+
+impl OutputSection for CopyrelSection {
+    fn section_header(&self) -> &SectionHeader {
+        &self.section.elf_header
+    }
+
+    fn wrapper_mut(&mut self) -> &mut SectionWrapper {
+        &mut self.section
+    }
+
+    fn name(&self) -> String {
+        self.section.name.clone()
+    }
+
+    fn copy_buf(&self, _ctx: &Context, buf: &mut [u8]) {
+        buf.fill(0);
+    }
+}
+
+impl CopyrelSection {
+    pub fn new(id: usize, is_relro: bool) -> Self {
+        let flags = SectionFlag::ALLOC as u64 | SectionFlag::WRITE as u64;
+        let section = if is_relro {
+            SectionWrapper::new_with(id, ".data.rel.ro", SectionType::PROGBITS, flags, 1)
+        } else {
+            SectionWrapper::new_with(id, ".bss", SectionType::NOBITS, flags, 1)
+        };
+        Self {
+            section,
+            symbols: vec![],
+            merged_into: None,
+        }
+    }
+    pub fn id(&self) -> usize {
+        *self.section.id.lock().unwrap()
+    }
+    /// Reserve `size` bytes aligned to `align`, return the offset.
+    pub fn reserve(&mut self, sym: ShareSymbol, size: u64, align: u64) -> usize {
+        let header = &mut self.section.elf_header;
+        let offset = header.size.next_multiple_of(align);
+        header.size = offset + size;
+        header.add_align = header.add_align.max(align);
+        self.symbols.push(sym);
+        offset as usize
+    }
+    /// The address of the reserved space.
+    pub fn addr(&self, ctx: &Context) -> u64 {
+        match self.merged_into {
+            Some((id, offset)) => ctx.section_addrs[&id] + offset,
+            None => self.section.elf_header.addr,
+        }
+    }
+    /// The index of the output section holding the reserved space.
+    pub fn shndx(&self, ctx: &Context) -> usize {
+        match self.merged_into {
+            Some((id, _)) => ctx.sections[&id].lock().unwrap().wrapper_mut().shndx,
+            None => self.section.shndx,
+        }
+    }
+}
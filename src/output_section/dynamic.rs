@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+
+use crate::{
+    context::Context,
+    linker::{
+        dynamic_tag::*, DynamicEntry, ElfSymbol, Rela, SectionFlag, SectionHeader, SectionType,
+    },
+    utils::read_struct::write_struct,
+};
+
+use super::output_section::{OutputSection, SectionWrapper};
+
+/// The .dynamic section, which tells the dynamic loader the libraries to
+/// load and where the tables the linker made for it are. The entries are
+/// computed when the section is written, once the addresses are known.
+#[derive(Debug)]
+pub struct DynamicSection {
+    section: SectionWrapper,
+    // offsets in .dynstr of the DT_NEEDED libraries and of DT_SONAME
+    needed: Vec<u32>,
+    soname: Option<u32>,
+}
+
+impl OutputSection for DynamicSection {
+    fn section_header(&self) -> &SectionHeader {
+        &self.section.elf_header
+    }
+
+    fn wrapper_mut(&mut self) -> &mut SectionWrapper {
+        &mut self.section
+    }
+
+    fn name(&self) -> String {
+        self.section.name.clone()
+    }
+
+    fn copy_buf(&self, ctx: &Context, buf: &mut [u8]) {
+        let mut data = vec![];
+        for entry in self.entries(ctx, true) {
+            write_struct(&mut data, &entry).unwrap();
+        }
+        buf.copy_from_slice(&data);
+    }
+}
+
+impl DynamicSection {
+    pub fn new(id: usize, needed: Vec<u32>, soname: Option<u32>) -> Self {
+        let flags = SectionFlag::ALLOC as u64 | SectionFlag::WRITE as u64;
+        let mut section = SectionWrapper::new_with(id, ".dynamic", SectionType::DYNAMIC, flags, 8);
+        section.elf_header.ent_size = size_of::<DynamicEntry>() as u64;
+        Self {
+            section,
+            needed,
+            soname,
+        }
+    }
+    pub fn id(&self) -> usize {
+        *self.section.id.lock().unwrap()
+    }
+    /// Size the section once the sections its entries point to exist.
+    pub fn update_size(&mut self, ctx: &Context) {
+        self.section.elf_header.size =
+            (self.entries(ctx, false).len() * size_of::<DynamicEntry>()) as u64;
+    }
+
+    /// The symbols are not placed yet when sizing the section, so their
+    /// addresses are only looked up with `resolve`.
+    fn entries(&self, ctx: &Context, resolve: bool) -> Vec<DynamicEntry> {
+        // this section is locked by the caller, skip it
+        let id = self.id();
+        let headers = ctx
+            .sections
+            .iter()
+            .filter(|(&sec_id, _)| sec_id != id)
+            .map(|(_, sec)| {
+                let sec = sec.lock().unwrap();
+                (sec.name(), sec.section_header().clone())
+            })
+            .filter(|(_, header)| header.size > 0)
+            .collect::<HashMap<_, _>>();
+
+        let mut entries = vec![];
+        let mut add = |tag: i64, val: u64| entries.push(DynamicEntry { tag, val });
+        for &name in &self.needed {
+            add(DT_NEEDED, name as u64);
+        }
+        if let Some(soname) = self.soname {
+            add(DT_SONAME, soname as u64);
+        }
+        for (tag, name) in [(DT_INIT, "_init"), (DT_FINI, "_fini")] {
+            let Some(sym) = ctx.get_symbol(name) else {
+                continue;
+            };
+            let sym = sym.lock().unwrap();
+            if sym.elf.is_some() && sym.is_alive && !sym.is_imported {
+                add(tag, if resolve { ctx.symbol_addr(&sym) } else { 0 });
+            }
+        }
+        for (name, tag, size_tag) in [
+            (".preinit_array", DT_PREINIT_ARRAY, DT_PREINIT_ARRAYSZ),
+            (".init_array", DT_INIT_ARRAY, DT_INIT_ARRAYSZ),
+            (".fini_array", DT_FINI_ARRAY, DT_FINI_ARRAYSZ),
+        ] {
+            if let Some(header) = headers.get(name) {
+                add(tag, header.addr);
+                add(size_tag, header.size);
+            }
+        }
+        if let Some(header) = headers.get(".hash") {
+            add(DT_HASH, header.addr);
+        }
+        if let Some(header) = headers.get(".dynstr") {
+            add(DT_STRTAB, header.addr);
+            add(DT_STRSZ, header.size);
+        }
+        if let Some(header) = headers.get(".dynsym") {
+            add(DT_SYMTAB, header.addr);
+            add(DT_SYMENT, size_of::<ElfSymbol>() as u64);
+        }
+        if let Some(header) = headers.get(".rela.dyn") {
+            add(DT_RELA, header.addr);
+            add(DT_RELASZ, header.size);
+            add(DT_RELAENT, size_of::<Rela>() as u64);
+        }
+        if let Some(header) = headers.get(".rela.plt") {
+            add(DT_JMPREL, header.addr);
+            add(DT_PLTRELSZ, header.size);
+            add(DT_PLTREL, DT_RELA as u64);
+        }
+        if let Some(header) = headers.get(".got.plt") {
+            add(DT_PLTGOT, header.addr);
+        }
+        if let Some(header) = headers.get(".gnu.version") {
+            add(DT_VERSYM, header.addr);
+        }
+        if let Some(header) = headers.get(".gnu.version_d") {
+            add(DT_VERDEF, header.addr);
+            add(DT_VERDEFNUM, header.info as u64);
+        }
+        if let Some(header) = headers.get(".gnu.version_r") {
+            add(DT_VERNEED, header.addr);
+            add(DT_VERNEEDNUM, header.info as u64);
+        }
+        // filled by the dynamic loader for debuggers
+        if !ctx.args.shared {
+            add(DT_DEBUG, 0);
+        }
+        add(DT_NULL, 0);
+        entries
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{
+    context::Context,
+    linker::{ElfSymbol, SectionFlag, SectionHeader, SectionType},
+    symbol::ShareSymbol,
+    utils::read_struct::write_struct,
+};
+
+use super::output_section::{OutputSection, SectionWrapper};
+
+/// The .dynsym section. Entries are prepared by `create_dynamic_symbols`,
+/// the values that depend on the layout are filled when it's written.
+#[derive(Debug)]
+pub struct DynsymSection {
+    section: SectionWrapper,
+    pub symbols: Vec<(ShareSymbol, ElfSymbol)>,
+}
+
+impl OutputSection for DynsymSection {
+    fn section_header(&self) -> &SectionHeader {
+        &self.section.elf_header
+    }
+
+    fn wrapper_mut(&mut self) -> &mut SectionWrapper {
+        &mut self.section
+    }
+
+    fn name(&self) -> String {
+        self.section.name.clone()
+    }
+
+    fn copy_buf(&self, ctx: &Context, buf: &mut [u8]) {
+        let mut data = vec![0; size_of::<ElfSymbol>()];
+        for (sym, elf_sym) in &self.symbols {
+            let sym = sym.lock().unwrap();
+            let mut elf_sym = elf_sym.clone();
+            if let Some((is_relro, _)) = sym.copyrel {
+                // the copy in the executable is the definition
+                elf_sym.val = ctx.copyrel_addr(&sym);
+                elf_sym.shndx = ctx.copyrel_section(is_relro).lock().unwrap().shndx(ctx) as u16;
+            } else if sym.is_canonical {
+                elf_sym.val = ctx
+                    .plt
+                    .as_ref()
+                    .unwrap()
+                    .lock()
+                    .unwrap()
+                    .entry_addr(sym.plt_idx.unwrap());
+            } else if let (false, Some(id)) = (sym.is_imported, sym.elf) {
+                let obj = ctx.get_object(id).unwrap();
+                let obj = obj.lock().unwrap();
+                if let Some((val, shndx)) = obj.symtab_value(ctx, sym.index) {
+                    elf_sym.val = val;
+                    elf_sym.shndx = shndx;
+                }
+            }
+            write_struct(&mut data, &elf_sym).unwrap();
+        }
+        buf.copy_from_slice(&data);
+    }
+}
+
+impl DynsymSection {
+    pub fn new(id: usize) -> Self {
+        let mut section = SectionWrapper::new_with(
+            id,
+            ".dynsym",
+            SectionType::DYNSYM,
+            SectionFlag::ALLOC as u64,
+            8,
+        );
+        section.elf_header.ent_size = size_of::<ElfSymbol>() as u64;
+        // index of the first non-local symbol
+        section.elf_header.info = 1;
+        section.elf_header.size = size_of::<ElfSymbol>() as u64;
+        Self {
+            section,
+            symbols: vec![],
+        }
+    }
+    pub fn add(&mut self, sym: ShareSymbol, elf_sym: ElfSymbol) {
+        sym.lock().unwrap().dynsym_idx = Some(self.symbols.len() + 1);
+        self.symbols.push((sym, elf_sym));
+        self.section.elf_header.size = (size_of::<ElfSymbol>() * (self.symbols.len() + 1)) as u64;
+    }
+}
@@ -0,0 +1,201 @@
+use std::{collections::HashMap, ops::Range};
+
+use crate::{
+    context::Context,
+    linker::{SectionFlag, SectionHeader, SectionType},
+    utils::leb128::{leb128_len, read_uleb128},
+};
+
+use super::output_section::{OutputSection, SectionWrapper};
+
+/// A CIE or an FDE copied from an input .eh_frame.
+#[derive(Debug)]
+pub struct EhFramePiece {
+    pub obj: usize,
+    pub shndx: usize,
+    // the record in the input section, including the length field
+    pub offset: u64,
+    pub size: u64,
+    // the relocations of the record, indices into the input relocations
+    pub rels: Range<usize>,
+    pub out_offset: u64,
+    // the output offset of the CIE of an FDE, None for a CIE
+    pub cie_out_offset: Option<u64>,
+}
+
+/// The output .eh_frame, built from the records of the input .eh_frame
+/// sections: identical CIEs are only written once, and the FDEs of the
+/// functions that are not in the output are dropped.
+#[derive(Debug)]
+pub struct EhFrameSection {
+    section: SectionWrapper,
+    pub pieces: Vec<EhFramePiece>,
+}
+
+impl OutputSection for EhFrameSection {
+    fn section_header(&self) -> &SectionHeader {
+        &self.section.elf_header
+    }
+
+    fn wrapper_mut(&mut self) -> &mut SectionWrapper {
+        &mut self.section
+    }
+
+    fn name(&self) -> String {
+        self.section.name.clone()
+    }
+
+    fn copy_buf(&self, ctx: &Context, buf: &mut [u8]) {
+        buf.fill(0);
+        for piece in &self.pieces {
+            let start = piece.out_offset as usize;
+            let end = (piece.out_offset + piece.size) as usize;
+            self.write_piece(ctx, piece, &mut buf[start..end]);
+        }
+    }
+}
+
+impl EhFrameSection {
+    pub fn new(id: usize) -> Self {
+        Self {
+            section: SectionWrapper::new_with(
+                id,
+                ".eh_frame",
+                SectionType::PROGBITS,
+                SectionFlag::ALLOC as u64,
+                8,
+            ),
+            pieces: vec![],
+        }
+    }
+    pub fn id(&self) -> usize {
+        *self.section.id.lock().unwrap()
+    }
+    /// Append a record, return its offset in this section.
+    pub fn add(&mut self, mut piece: EhFramePiece) -> u64 {
+        let header = &mut self.section.elf_header;
+        let offset = header.size;
+        header.size += piece.size;
+        piece.out_offset = offset;
+        self.pieces.push(piece);
+        offset
+    }
+    /// Copy a record into `buf` and relocate it.
+    fn write_piece(&self, ctx: &Context, piece: &EhFramePiece, buf: &mut [u8]) {
+        let obj = ctx.get_object(piece.obj).unwrap();
+        let obj = obj.lock().unwrap();
+        let data = &obj.section_info.sections[piece.shndx]
+            .as_ref()
+            .unwrap()
+            .data;
+        let start = piece.offset as usize;
+        buf.copy_from_slice(&data[start..start + piece.size as usize]);
+        // the distance from the CIE pointer back to the CIE
+        if let Some(cie) = piece.cie_out_offset {
+            let ptr = (piece.out_offset + 4 - cie) as u32;
+            buf[4..8].copy_from_slice(&ptr.to_le_bytes());
+        }
+        let relas = &obj.section_info.relocations[piece.shndx][piece.rels.clone()];
+        let addr = self.section.elf_header.addr + piece.out_offset;
+        obj.apply_relocations_at(ctx, piece.shndx, relas, piece.offset, buf, addr);
+    }
+    pub fn num_fdes(&self) -> usize {
+        self.pieces
+            .iter()
+            .filter(|piece| piece.cie_out_offset.is_some())
+            .count()
+    }
+    /// (initial location, address) of the FDEs, sorted by location.
+    pub fn fde_table(&self, ctx: &Context) -> Vec<(u64, u64)> {
+        let base = self.section.elf_header.addr;
+        let mut encodings = HashMap::new();
+        let mut table = vec![];
+        for piece in &self.pieces {
+            let mut buf = vec![0; piece.size as usize];
+            self.write_piece(ctx, piece, &mut buf);
+            let addr = base + piece.out_offset;
+            match piece.cie_out_offset {
+                None => {
+                    encodings.insert(piece.out_offset, fde_encoding(&buf));
+                }
+                Some(cie) => {
+                    let pc = read_encoded(&buf[8..], encodings[&cie], addr + 8);
+                    table.push((pc, addr));
+                }
+            }
+        }
+        table.sort();
+        table
+    }
+    /// The zero length record that ends the section.
+    pub fn add_terminator(&mut self) {
+        self.section.elf_header.size += 4;
+    }
+}
+
+// DWARF pointer encodings
+const DW_EH_PE_ABSPTR: u8 = 0x00;
+const DW_EH_PE_UDATA2: u8 = 0x02;
+const DW_EH_PE_UDATA4: u8 = 0x03;
+const DW_EH_PE_UDATA8: u8 = 0x04;
+const DW_EH_PE_SDATA2: u8 = 0x0a;
+const DW_EH_PE_SDATA4: u8 = 0x0b;
+const DW_EH_PE_SDATA8: u8 = 0x0c;
+const DW_EH_PE_PCREL: u8 = 0x10;
+
+/// The encoding of the addresses in the FDEs of a CIE, given by its R
+/// augmentation.
+fn fde_encoding(cie: &[u8]) -> u8 {
+    let version = cie[8];
+    let aug_len = cie[9..].iter().position(|&c| c == 0).unwrap();
+    let aug = &cie[9..9 + aug_len];
+    let mut pos = 9 + aug_len + 1;
+    // code alignment, data alignment and return address register
+    pos += leb128_len(&cie[pos..]);
+    pos += leb128_len(&cie[pos..]);
+    pos += if version == 1 {
+        1
+    } else {
+        leb128_len(&cie[pos..])
+    };
+    if aug.first() != Some(&b'z') {
+        return DW_EH_PE_ABSPTR;
+    }
+    pos += read_uleb128(&cie[pos..]).1;
+    for &c in &aug[1..] {
+        match c {
+            b'R' => return cie[pos],
+            b'P' => pos += 1 + encoded_size(cie[pos]),
+            b'L' => pos += 1,
+            _ => {}
+        }
+    }
+    DW_EH_PE_ABSPTR
+}
+
+fn encoded_size(enc: u8) -> usize {
+    match enc & 0x0f {
+        DW_EH_PE_ABSPTR | DW_EH_PE_UDATA8 | DW_EH_PE_SDATA8 => 8,
+        DW_EH_PE_UDATA4 | DW_EH_PE_SDATA4 => 4,
+        DW_EH_PE_UDATA2 | DW_EH_PE_SDATA2 => 2,
+        _ => panic!("unsupported pointer encoding {:#x} in .eh_frame", enc),
+    }
+}
+
+/// Decode the pointer at the start of `data`, which is at `addr`.
+fn read_encoded(data: &[u8], enc: u8, addr: u64) -> u64 {
+    let size = encoded_size(enc);
+    let mut bytes = [0; 8];
+    bytes[..size].copy_from_slice(&data[..size]);
+    let mut val = u64::from_le_bytes(bytes);
+    // sign extend
+    if enc & 0x08 != 0 && size < 8 {
+        let shift = 64 - size * 8;
+        val = (((val << shift) as i64) >> shift) as u64;
+    }
+    match enc & 0x70 {
+        0 => val,
+        DW_EH_PE_PCREL => addr.wrapping_add(val),
+        _ => panic!("unsupported pointer encoding {:#x} in .eh_frame", enc),
+    }
+}
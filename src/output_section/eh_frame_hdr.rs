@@ -0,0 +1,59 @@
+use crate::{
+    context::Context,
+    linker::{SectionFlag, SectionHeader, SectionType},
+};
+
+use super::output_section::{OutputSection, SectionWrapper};
+
+/// .eh_frame_hdr, which points to .eh_frame and holds a table of the
+/// FDEs sorted by address, for the unwinder to binary search.
+#[derive(Debug)]
+pub struct EhFrameHdrSection {
+    section: SectionWrapper,
+}
+
+impl OutputSection for EhFrameHdrSection {
+    fn section_header(&self) -> &SectionHeader {
+        &self.section.elf_header
+    }
+
+    fn wrapper_mut(&mut self) -> &mut SectionWrapper {
+        &mut self.section
+    }
+
+    fn name(&self) -> String {
+        self.section.name.clone()
+    }
+
+    fn copy_buf(&self, ctx: &Context, buf: &mut [u8]) {
+        let eh_frame = ctx.eh_frame.as_ref().unwrap().lock().unwrap();
+        let table = eh_frame.fde_table(ctx);
+        let addr = self.section.elf_header.addr as i64;
+        let eh_frame_addr = eh_frame.section_header().addr as i64;
+        // version, then the encodings of the .eh_frame pointer
+        // (pcrel sdata4), of the FDE count (udata4) and of the table
+        // entries (datarel sdata4)
+        buf[..4].copy_from_slice(&[1, 0x1b, 0x03, 0x3b]);
+        buf[4..8].copy_from_slice(&((eh_frame_addr - (addr + 4)) as i32).to_le_bytes());
+        buf[8..12].copy_from_slice(&(table.len() as u32).to_le_bytes());
+        for (i, (pc, fde)) in table.into_iter().enumerate() {
+            let entry = &mut buf[12 + i * 8..12 + i * 8 + 8];
+            entry[..4].copy_from_slice(&((pc as i64 - addr) as i32).to_le_bytes());
+            entry[4..].copy_from_slice(&((fde as i64 - addr) as i32).to_le_bytes());
+        }
+    }
+}
+
+impl EhFrameHdrSection {
+    pub fn new(id: usize, num_fdes: usize) -> Self {
+        let mut section = SectionWrapper::new_with(
+            id,
+            ".eh_frame_hdr",
+            SectionType::PROGBITS,
+            SectionFlag::ALLOC as u64,
+            4,
+        );
+        section.elf_header.size = 12 + 8 * num_fdes as u64;
+        Self { section }
+    }
+}
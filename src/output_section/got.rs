@@ -0,0 +1,181 @@
+use crate::{
+    context::Context,
+    linker::{SectionFlag, SectionHeader, SectionType},
+    symbol::ShareSymbol,
+};
+
+use super::output_section::{OutputSection, SectionWrapper};
+
+/// An entry of .got, the TLS ones take two slots.
+#[derive(Debug)]
+pub enum GotEntry {
+    // the address of the symbol
+    Addr(ShareSymbol),
+    // the offset of a TLS symbol from the thread pointer
+    TpOff(ShareSymbol),
+    // the module id and the offset in the module of a TLS symbol,
+    // the argument of __tls_get_addr
+    TlsGd(ShareSymbol),
+    // the module id, for the local-dynamic model
+    TlsLd,
+}
+
+/// The .got section, the slots of imported symbols are filled by the
+/// dynamic loader through R_X86_64_GLOB_DAT and the TLS relocations.
+#[derive(Debug)]
+pub struct GotSection {
+    section: SectionWrapper,
+    pub entries: Vec<GotEntry>,
+    pub tlsld_idx: Option<usize>,
+}
+
+impl OutputSection for GotSection {
+    fn section_header(&self) -> &SectionHeader {
+        &self.section.elf_header
+    }
+
+    fn wrapper_mut(&mut self) -> &mut SectionWrapper {
+        &mut self.section
+    }
+
+    fn name(&self) -> String {
+        self.section.name.clone()
+    }
+
+    fn copy_buf(&self, ctx: &Context, buf: &mut [u8]) {
+        buf.fill(0);
+        // the module of the executable is always the first one
+        let module_id = if ctx.args.shared { 0 } else { 1 };
+        let mut slot = 0;
+        let mut write = |val: u64| {
+            buf[slot * 8..slot * 8 + 8].copy_from_slice(&val.to_le_bytes());
+            slot += 1;
+        };
+        for entry in &self.entries {
+            match entry {
+                GotEntry::Addr(sym) => {
+                    let sym = sym.lock().unwrap();
+                    // set by GLOB_DAT or IRELATIVE at runtime
+                    if sym.is_imported || sym.is_iplt() {
+                        write(0);
+                    } else {
+                        write(ctx.symbol_addr(&sym));
+                    }
+                }
+                GotEntry::TpOff(sym) => {
+                    let sym = sym.lock().unwrap();
+                    if sym.is_imported {
+                        write(0);
+                    } else {
+                        write(ctx.symbol_addr(&sym).wrapping_sub(ctx.tls_end));
+                    }
+                }
+                GotEntry::TlsGd(sym) => {
+                    let sym = sym.lock().unwrap();
+                    if sym.is_imported {
+                        write(0);
+                        write(0);
+                    } else {
+                        write(module_id);
+                        write(ctx.symbol_addr(&sym) - ctx.tls_begin);
+                    }
+                }
+                GotEntry::TlsLd => {
+                    write(module_id);
+                    write(0);
+                }
+            }
+        }
+    }
+}
+
+impl GotSection {
+    pub fn new(id: usize) -> Self {
+        let flags = SectionFlag::ALLOC as u64 | SectionFlag::WRITE as u64;
+        Self {
+            section: SectionWrapper::new_with(id, ".got", SectionType::PROGBITS, flags, 8),
+            entries: vec![],
+            tlsld_idx: None,
+        }
+    }
+    fn add(&mut self, entry: GotEntry, slots: usize) -> usize {
+        let idx = (self.section.elf_header.size / 8) as usize;
+        self.entries.push(entry);
+        self.section.elf_header.size += 8 * slots as u64;
+        idx
+    }
+    pub fn add_symbol(&mut self, sym: ShareSymbol) {
+        let idx = self.add(GotEntry::Addr(sym.clone()), 1);
+        sym.lock().unwrap().got_idx = Some(idx);
+    }
+    pub fn add_gottp(&mut self, sym: ShareSymbol) {
+        let idx = self.add(GotEntry::TpOff(sym.clone()), 1);
+        sym.lock().unwrap().gottp_idx = Some(idx);
+    }
+    pub fn add_tlsgd(&mut self, sym: ShareSymbol) {
+        let idx = self.add(GotEntry::TlsGd(sym.clone()), 2);
+        sym.lock().unwrap().tlsgd_idx = Some(idx);
+    }
+    pub fn add_tlsld(&mut self) {
+        if self.tlsld_idx.is_none() {
+            self.tlsld_idx = Some(self.add(GotEntry::TlsLd, 2));
+        }
+    }
+    pub fn entry_addr(&self, idx: usize) -> u64 {
+        self.section.elf_header.addr + 8 * idx as u64
+    }
+}
+
+/// The .got.plt section, the first three slots are reserved for the
+/// dynamic loader, followed by one slot per PLT entry.
+#[derive(Debug)]
+pub struct GotPltSection {
+    section: SectionWrapper,
+}
+
+impl OutputSection for GotPltSection {
+    fn section_header(&self) -> &SectionHeader {
+        &self.section.elf_header
+    }
+
+    fn wrapper_mut(&mut self) -> &mut SectionWrapper {
+        &mut self.section
+    }
+
+    fn name(&self) -> String {
+        self.section.name.clone()
+    }
+
+    fn copy_buf(&self, ctx: &Context, buf: &mut [u8]) {
+        buf.fill(0);
+        // the first slot holds the address of .dynamic
+        if let Some(ref dynamic) = ctx.dynamic {
+            let addr = dynamic.lock().unwrap().section_header().addr;
+            buf[..8].copy_from_slice(&addr.to_le_bytes());
+        }
+        let plt = ctx.plt.as_ref().unwrap().lock().unwrap();
+        for i in 0..plt.symbols.len() {
+            // lazy binding, jump back to the push instruction of the PLT entry
+            let addr = plt.entry_addr(i) + 6;
+            let offset = 8 * (Self::HEADER_SIZE + i);
+            buf[offset..offset + 8].copy_from_slice(&addr.to_le_bytes());
+        }
+    }
+}
+
+impl GotPltSection {
+    pub const HEADER_SIZE: usize = 3;
+
+    pub fn new(id: usize) -> Self {
+        let flags = SectionFlag::ALLOC as u64 | SectionFlag::WRITE as u64;
+        let mut section = SectionWrapper::new_with(id, ".got.plt", SectionType::PROGBITS, flags, 8);
+        section.elf_header.size = 8 * Self::HEADER_SIZE as u64;
+        Self { section }
+    }
+    pub fn set_entries(&mut self, num: usize) {
+        self.section.elf_header.size = 8 * (Self::HEADER_SIZE + num) as u64;
+    }
+    pub fn entry_addr(&self, plt_idx: usize) -> u64 {
+        self.section.elf_header.addr + 8 * (Self::HEADER_SIZE + plt_idx) as u64
+    }
+}
@@ -1,6 +1,6 @@
 use std::{collections::HashMap, rc::Rc, sync::Mutex};
 
-use crate::{context::Context, Id};
+use crate::{context::Context, linker::SectionHeader};
 
 use super::output_section::{OutputSection, SectionWrapper, ShareOutputSection};
 
@@ -9,18 +9,34 @@ pub type ShareSectionFragment = Rc<Mutex<SectionFragment>>;
 pub struct MergedSection {
     section: SectionWrapper,
     map: HashMap<FragmentData, ShareSectionFragment>,
+    // the fragments in insertion order, which is also their output order
+    fragments: Vec<(FragmentData, ShareSectionFragment)>,
 }
 
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub enum FragmentData {
-    Str(String),
+    // a string including its terminator
+    Str(Vec<u8>),
     Constant(Vec<u8>),
 }
 
+impl FragmentData {
+    pub fn bytes(&self) -> &[u8] {
+        match self {
+            Self::Str(data) | Self::Constant(data) => data,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct SectionFragment {
-    section_id: usize,
+    pub section_id: usize,
     align: usize,
+    // whether a kept input section holds the piece, the pieces of dropped
+    // sections are inserted all the same
+    pub is_alive: bool,
+    // offset in the output section, set by `MergedSection::assign_offsets`
+    pub offset: u64,
 }
 
 impl SectionFragment {
@@ -28,6 +44,8 @@ impl SectionFragment {
         Rc::new(Mutex::new(Self {
             section_id: id,
             align,
+            is_alive: false,
+            offset: 0,
         }))
     }
 }
@@ -41,13 +59,30 @@ impl OutputSection for MergedSection {
         Some(self)
     }
 
-    fn section_header(&self) -> &crate::linker::SectionHeader {
+    fn section_header(&self) -> &SectionHeader {
         &self.section.elf_header
     }
 
+    fn wrapper_mut(&mut self) -> &mut SectionWrapper {
+        &mut self.section
+    }
+
     fn name(&self) -> String {
         self.section.name.clone()
     }
+
+    fn copy_buf(&self, _ctx: &Context, buf: &mut [u8]) {
+        buf.fill(0);
+        for (data, frag) in &self.fragments {
+            let frag = frag.lock().unwrap();
+            if !frag.is_alive {
+                continue;
+            }
+            let offset = frag.offset as usize;
+            let data = data.bytes();
+            buf[offset..offset + data.len()].copy_from_slice(data);
+        }
+    }
 }
 
 impl MergedSection {
@@ -55,6 +90,7 @@ impl MergedSection {
         let sec = MergedSection {
             section,
             map: HashMap::default(),
+            fragments: vec![],
         };
         let sec = Rc::new(Mutex::new(sec));
         sec
@@ -76,6 +112,26 @@ impl MergedSection {
         }
         let sec_frag = SectionFragment::new(self.id(), align);
         self.map.insert(frag.clone(), sec_frag.clone());
+        self.fragments.push((frag.clone(), sec_frag.clone()));
         sec_frag
     }
+    /// Place the live fragments one after another and compute the section
+    /// size.
+    pub fn assign_offsets(&mut self) {
+        let mut offset = 0u64;
+        let mut max_align = 1;
+        for (data, frag) in &self.fragments {
+            let mut frag = frag.lock().unwrap();
+            if !frag.is_alive {
+                continue;
+            }
+            let align = frag.align.max(1) as u64;
+            offset = offset.next_multiple_of(align);
+            frag.offset = offset;
+            offset += data.bytes().len() as u64;
+            max_align = max_align.max(align);
+        }
+        self.section.elf_header.size = offset;
+        self.section.elf_header.add_align = max_align;
+    }
 }
@@ -1,2 +1,13 @@
+pub mod copyrel;
+pub mod dynamic;
+pub mod dynsym;
+pub mod eh_frame;
+pub mod eh_frame_hdr;
+pub mod got;
 pub mod merged_section;
 pub mod output_section;
+pub mod plt;
+pub mod regular_section;
+pub mod rela;
+pub mod synthetic_section;
+pub mod version;
@@ -18,6 +18,9 @@ pub trait OutputSection {
         None
     }
     fn section_header(&self) -> &SectionHeader;
+    /// Used by the layout to assign the address, the file offset and
+    /// the index of the section.
+    fn wrapper_mut(&mut self) -> &mut SectionWrapper;
 
     fn name(&self) -> String;
     fn typ(&self) -> SectionType {
@@ -26,6 +29,14 @@ pub trait OutputSection {
     fn flags(&self) -> u64 {
         self.section_header().flags
     }
+    fn data(&self) -> &[u8] {
+        &[]
+    }
+    /// Write the contents of the section into `buf`, which is as large
+    /// as the section.
+    fn copy_buf(&self, _ctx: &Context, buf: &mut [u8]) {
+        buf.copy_from_slice(self.data());
+    }
 }
 
 #[derive(Debug)]
@@ -33,15 +44,26 @@ pub struct SectionWrapper {
     pub name: String,
     pub id: Id,
     pub elf_header: SectionHeader,
+    // the index in the output section header table
+    pub shndx: usize,
 }
 
 impl SectionWrapper {
+    pub fn new_with(id: usize, name: &str, typ: SectionType, flags: u64, align: u64) -> Self {
+        let mut section = Self::new(id);
+        section.name = name.to_string();
+        section.elf_header._type = typ;
+        section.elf_header.flags = flags;
+        section.elf_header.add_align = align;
+        section
+    }
     pub fn new(id: usize) -> Self {
         let id = Rc::new(Mutex::new(id));
         Self {
             id,
             name: "".to_string(),
             elf_header: SectionHeader::default(),
+            shndx: 0,
         }
     }
 }
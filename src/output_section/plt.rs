@@ -0,0 +1,135 @@
+use crate::{
+    context::Context,
+    linker::{SectionFlag, SectionHeader, SectionType},
+    symbol::ShareSymbol,
+};
+
+use super::output_section::{OutputSection, SectionWrapper};
+
+/// The .plt section, a lazy binding PLT header followed by one entry
+/// per imported function.
+#[derive(Debug)]
+pub struct PltSection {
+    section: SectionWrapper,
+    pub symbols: Vec<ShareSymbol>,
+}
+
+impl OutputSection for PltSection {
+    fn section_header(&self) -> &SectionHeader {
+        &self.section.elf_header
+    }
+
+    fn wrapper_mut(&mut self) -> &mut SectionWrapper {
+        &mut self.section
+    }
+
+    fn name(&self) -> String {
+        self.section.name.clone()
+    }
+
+    fn copy_buf(&self, ctx: &Context, buf: &mut [u8]) {
+        let got_plt = ctx.got_plt.as_ref().unwrap().lock().unwrap();
+        let plt0 = self.section.elf_header.addr;
+        let got_plt_addr = got_plt.section_header().addr;
+
+        // pushq GOTPLT+8(%rip); jmp *GOTPLT+16(%rip); nopl 0(%rax)
+        buf[..Self::ENTRY_SIZE].copy_from_slice(&[
+            0xff, 0x35, 0, 0, 0, 0, 0xff, 0x25, 0, 0, 0, 0, 0x0f, 0x1f, 0x40, 0x00,
+        ]);
+        let disp = (got_plt_addr + 8) as i64 - (plt0 + 6) as i64;
+        buf[2..6].copy_from_slice(&(disp as i32).to_le_bytes());
+        let disp = (got_plt_addr + 16) as i64 - (plt0 + 12) as i64;
+        buf[8..12].copy_from_slice(&(disp as i32).to_le_bytes());
+
+        for i in 0..self.symbols.len() {
+            let ent = self.entry_addr(i);
+            let buf = &mut buf[Self::ENTRY_SIZE * (i + 1)..Self::ENTRY_SIZE * (i + 2)];
+            // jmp *slot(%rip); pushq $i; jmp PLT0
+            buf.copy_from_slice(&[0xff, 0x25, 0, 0, 0, 0, 0x68, 0, 0, 0, 0, 0xe9, 0, 0, 0, 0]);
+            let disp = got_plt.entry_addr(i) as i64 - (ent + 6) as i64;
+            buf[2..6].copy_from_slice(&(disp as i32).to_le_bytes());
+            buf[7..11].copy_from_slice(&(i as u32).to_le_bytes());
+            let disp = plt0 as i64 - (ent + 16) as i64;
+            buf[12..16].copy_from_slice(&(disp as i32).to_le_bytes());
+        }
+    }
+}
+
+impl PltSection {
+    pub const ENTRY_SIZE: usize = 16;
+
+    pub fn new(id: usize) -> Self {
+        let flags = SectionFlag::ALLOC as u64 | SectionFlag::EXECINSTR as u64;
+        let mut section = SectionWrapper::new_with(id, ".plt", SectionType::PROGBITS, flags, 16);
+        section.elf_header.size = Self::ENTRY_SIZE as u64;
+        Self {
+            section,
+            symbols: vec![],
+        }
+    }
+    pub fn add_symbol(&mut self, sym: ShareSymbol) {
+        sym.lock().unwrap().plt_idx = Some(self.symbols.len());
+        self.symbols.push(sym);
+        self.section.elf_header.size = (Self::ENTRY_SIZE * (self.symbols.len() + 1)) as u64;
+    }
+    pub fn entry_addr(&self, idx: usize) -> u64 {
+        self.section.elf_header.addr + (Self::ENTRY_SIZE * (idx + 1)) as u64
+    }
+}
+
+/// The .iplt section, one entry per IFUNC defined by the output. The
+/// entry jumps through a GOT slot which an R_X86_64_IRELATIVE relocation
+/// sets to the implementation picked by the resolver.
+#[derive(Debug)]
+pub struct IpltSection {
+    section: SectionWrapper,
+    pub symbols: Vec<ShareSymbol>,
+}
+
+impl OutputSection for IpltSection {
+    fn section_header(&self) -> &SectionHeader {
+        &self.section.elf_header
+    }
+
+    fn wrapper_mut(&mut self) -> &mut SectionWrapper {
+        &mut self.section
+    }
+
+    fn name(&self) -> String {
+        self.section.name.clone()
+    }
+
+    fn copy_buf(&self, ctx: &Context, buf: &mut [u8]) {
+        let got = ctx.got.as_ref().unwrap().lock().unwrap();
+        for (i, sym) in self.symbols.iter().enumerate() {
+            let ent = self.entry_addr(i);
+            let buf = &mut buf[Self::ENTRY_SIZE * i..Self::ENTRY_SIZE * (i + 1)];
+            // jmp *slot(%rip); padded with int3
+            buf.fill(0xcc);
+            buf[..2].copy_from_slice(&[0xff, 0x25]);
+            let slot = got.entry_addr(sym.lock().unwrap().got_idx.unwrap());
+            let disp = slot as i64 - (ent + 6) as i64;
+            buf[2..6].copy_from_slice(&(disp as i32).to_le_bytes());
+        }
+    }
+}
+
+impl IpltSection {
+    pub const ENTRY_SIZE: usize = 16;
+
+    pub fn new(id: usize) -> Self {
+        let flags = SectionFlag::ALLOC as u64 | SectionFlag::EXECINSTR as u64;
+        Self {
+            section: SectionWrapper::new_with(id, ".iplt", SectionType::PROGBITS, flags, 16),
+            symbols: vec![],
+        }
+    }
+    pub fn add_symbol(&mut self, sym: ShareSymbol) {
+        sym.lock().unwrap().plt_idx = Some(self.symbols.len());
+        self.symbols.push(sym);
+        self.section.elf_header.size = (Self::ENTRY_SIZE * self.symbols.len()) as u64;
+    }
+    pub fn entry_addr(&self, idx: usize) -> u64 {
+        self.section.elf_header.addr + (Self::ENTRY_SIZE * idx) as u64
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{
+    context::Context,
+    linker::{SectionHeader, SectionType},
+};
+
+use super::output_section::{OutputSection, SectionWrapper};
+
+/// An output section made of input sections, e.g. all the .text.*
+/// sections of the objects go into .text.
+#[derive(Debug)]
+pub struct RegularSection {
+    section: SectionWrapper,
+    // (object id, section index) of the input sections, in output order
+    pub members: Vec<(usize, usize)>,
+}
+
+impl OutputSection for RegularSection {
+    fn section_header(&self) -> &SectionHeader {
+        &self.section.elf_header
+    }
+
+    fn wrapper_mut(&mut self) -> &mut SectionWrapper {
+        &mut self.section
+    }
+
+    fn name(&self) -> String {
+        self.section.name.clone()
+    }
+
+    fn copy_buf(&self, ctx: &Context, buf: &mut [u8]) {
+        for &(obj, shndx) in &self.members {
+            let obj = ctx.get_object(obj).unwrap();
+            let obj = obj.lock().unwrap();
+            let sec = obj.section_info.sections[shndx].as_ref().unwrap();
+            let offset = sec.offset as usize;
+            let size = obj.section_info.elf_sections[shndx].size as usize;
+            let buf = &mut buf[offset..offset + size];
+            buf.copy_from_slice(&sec.data);
+            obj.apply_relocations(ctx, shndx, buf, self.section.elf_header.addr + sec.offset);
+        }
+    }
+}
+
+impl RegularSection {
+    pub fn new(id: usize, name: &str, typ: SectionType, flags: u64) -> Self {
+        Self {
+            section: SectionWrapper::new_with(id, name, typ, flags, 1),
+            members: vec![],
+        }
+    }
+    pub fn id(&self) -> usize {
+        *self.section.id.lock().unwrap()
+    }
+    /// Append an input section, return its offset in this section.
+    pub fn add(&mut self, obj: usize, shndx: usize, size: u64, align: u64) -> u64 {
+        let header = &mut self.section.elf_header;
+        let align = align.max(1);
+        let offset = header.size.next_multiple_of(align);
+        header.size = offset + size;
+        header.add_align = header.add_align.max(align);
+        self.members.push((obj, shndx));
+        offset
+    }
+    /// Append `size` bytes filled by the linker, return their offset.
+    pub fn reserve(&mut self, size: u64, align: u64) -> u64 {
+        let header = &mut self.section.elf_header;
+        let offset = header.size.next_multiple_of(align);
+        header.size = offset + size;
+        header.add_align = header.add_align.max(align);
+        offset
+    }
+}
@@ -0,0 +1,189 @@
+use crate::{
+    context::Context,
+    linker::{Rela, SectionFlag, SectionHeader, SectionType},
+    relocation::RelType,
+    symbol::ShareSymbol,
+    utils::read_struct::write_struct,
+};
+
+use super::output_section::{OutputSection, SectionWrapper};
+
+/// A relocation for the dynamic loader. The place and the symbol index
+/// are only known once the output is laid out, so they are computed
+/// when the section is written.
+#[derive(Debug)]
+pub enum DynamicReloc {
+    Copy(ShareSymbol),
+    GlobDat(ShareSymbol),
+    // the GOT slot of a symbol defined by a position independent output
+    Relative(ShareSymbol),
+    // the relocation `idx` of the input section `shndx` of object `obj`,
+    // an absolute address in a position independent output
+    Abs64 {
+        obj: usize,
+        shndx: usize,
+        idx: usize,
+    },
+    JumpSlot(ShareSymbol),
+    // the GOT slot of a local IFUNC, set to what its resolver returns
+    IRelative(ShareSymbol),
+    // the GOT slots of TLS symbols
+    TpOff(ShareSymbol),
+    DtpMod(ShareSymbol),
+    DtpOff(ShareSymbol),
+    // the module id of the output itself for local-dynamic accesses
+    TlsLd,
+}
+
+/// .rela.dyn, .rela.plt or .rela.iplt
+#[derive(Debug)]
+pub struct RelaSection {
+    section: SectionWrapper,
+    pub relocs: Vec<DynamicReloc>,
+}
+
+impl OutputSection for RelaSection {
+    fn section_header(&self) -> &SectionHeader {
+        &self.section.elf_header
+    }
+
+    fn wrapper_mut(&mut self) -> &mut SectionWrapper {
+        &mut self.section
+    }
+
+    fn name(&self) -> String {
+        self.section.name.clone()
+    }
+
+    fn copy_buf(&self, ctx: &Context, buf: &mut [u8]) {
+        let mut data = vec![];
+        for reloc in &self.relocs {
+            let rela = match reloc {
+                DynamicReloc::Copy(sym) => {
+                    let sym = sym.lock().unwrap();
+                    Rela::new(
+                        ctx.copyrel_addr(&sym),
+                        sym.dynsym_idx.unwrap(),
+                        RelType::R_X86_64_COPY as u32,
+                        0,
+                    )
+                }
+                DynamicReloc::GlobDat(sym) => {
+                    let sym = sym.lock().unwrap();
+                    let got = ctx.got.as_ref().unwrap().lock().unwrap();
+                    Rela::new(
+                        got.entry_addr(sym.got_idx.unwrap()),
+                        sym.dynsym_idx.unwrap(),
+                        RelType::R_X86_64_GLOB_DAT as u32,
+                        0,
+                    )
+                }
+                DynamicReloc::Relative(sym) => {
+                    let sym = sym.lock().unwrap();
+                    let got = ctx.got.as_ref().unwrap().lock().unwrap();
+                    Rela::new(
+                        got.entry_addr(sym.got_idx.unwrap()),
+                        0,
+                        RelType::R_X86_64_RELATIVE as u32,
+                        ctx.symbol_addr(&sym) as i64,
+                    )
+                }
+                DynamicReloc::Abs64 { obj, shndx, idx } => {
+                    let obj = ctx.get_object(*obj).unwrap();
+                    let obj = obj.lock().unwrap();
+                    obj.dynamic_reloc(ctx, *shndx, *idx)
+                }
+                DynamicReloc::JumpSlot(sym) => {
+                    let sym = sym.lock().unwrap();
+                    let got_plt = ctx.got_plt.as_ref().unwrap().lock().unwrap();
+                    Rela::new(
+                        got_plt.entry_addr(sym.plt_idx.unwrap()),
+                        sym.dynsym_idx.unwrap(),
+                        RelType::R_X86_64_JUMP_SLOT as u32,
+                        0,
+                    )
+                }
+                DynamicReloc::IRelative(sym) => {
+                    let sym = sym.lock().unwrap();
+                    let got = ctx.got.as_ref().unwrap().lock().unwrap();
+                    // the addend is the resolver, the definition of the IFUNC
+                    Rela::new(
+                        got.entry_addr(sym.got_idx.unwrap()),
+                        0,
+                        RelType::R_X86_64_IRELATIVE as u32,
+                        ctx.definition_addr(&sym) as i64,
+                    )
+                }
+                DynamicReloc::TpOff(sym) => {
+                    let sym = sym.lock().unwrap();
+                    let got = ctx.got.as_ref().unwrap().lock().unwrap();
+                    // a local symbol is relative to the TLS block of the
+                    // output itself, which is symbol index 0
+                    let (idx, addend) = if sym.is_preemptible() {
+                        (sym.dynsym_idx.unwrap(), 0)
+                    } else {
+                        (0, (ctx.symbol_addr(&sym) - ctx.tls_begin) as i64)
+                    };
+                    Rela::new(
+                        got.entry_addr(sym.gottp_idx.unwrap()),
+                        idx,
+                        RelType::R_X86_64_TPOFF64 as u32,
+                        addend,
+                    )
+                }
+                DynamicReloc::DtpMod(sym) => {
+                    let sym = sym.lock().unwrap();
+                    let got = ctx.got.as_ref().unwrap().lock().unwrap();
+                    // a symbol defined by a shared output refers to its
+                    // own module, which is symbol index 0
+                    Rela::new(
+                        got.entry_addr(sym.tlsgd_idx.unwrap()),
+                        sym.dynsym_idx.unwrap_or(0),
+                        RelType::R_X86_64_DTPMOD64 as u32,
+                        0,
+                    )
+                }
+                DynamicReloc::DtpOff(sym) => {
+                    let sym = sym.lock().unwrap();
+                    let got = ctx.got.as_ref().unwrap().lock().unwrap();
+                    Rela::new(
+                        got.entry_addr(sym.tlsgd_idx.unwrap() + 1),
+                        sym.dynsym_idx.unwrap(),
+                        RelType::R_X86_64_DTPOFF64 as u32,
+                        0,
+                    )
+                }
+                DynamicReloc::TlsLd => {
+                    let got = ctx.got.as_ref().unwrap().lock().unwrap();
+                    Rela::new(
+                        got.entry_addr(got.tlsld_idx.unwrap()),
+                        0,
+                        RelType::R_X86_64_DTPMOD64 as u32,
+                        0,
+                    )
+                }
+            };
+            write_struct(&mut data, &rela).unwrap();
+        }
+        buf.copy_from_slice(&data);
+    }
+}
+
+impl RelaSection {
+    pub fn new(id: usize, name: &str) -> Self {
+        let mut section =
+            SectionWrapper::new_with(id, name, SectionType::RELA, SectionFlag::ALLOC as u64, 8);
+        section.elf_header.ent_size = size_of::<Rela>() as u64;
+        Self {
+            section,
+            relocs: vec![],
+        }
+    }
+    pub fn id(&self) -> usize {
+        *self.section.id.lock().unwrap()
+    }
+    pub fn add(&mut self, reloc: DynamicReloc) {
+        self.relocs.push(reloc);
+        self.section.elf_header.size = (size_of::<Rela>() * self.relocs.len()) as u64;
+    }
+}
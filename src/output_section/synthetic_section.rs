@@ -0,0 +1,45 @@
+use crate::linker::{SectionHeader, SectionType};
+
+use super::output_section::{OutputSection, SectionWrapper};
+
+/// An output section whose contents are generated by the linker
+/// rather than copied from input sections, e.g. `.dynsym`.
+#[derive(Debug)]
+pub struct SyntheticSection {
+    section: SectionWrapper,
+    data: Vec<u8>,
+}
+
+impl OutputSection for SyntheticSection {
+    fn section_header(&self) -> &SectionHeader {
+        &self.section.elf_header
+    }
+
+    fn wrapper_mut(&mut self) -> &mut SectionWrapper {
+        &mut self.section
+    }
+
+    fn name(&self) -> String {
+        self.section.name.clone()
+    }
+
+    fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl SyntheticSection {
+    pub fn new(id: usize, name: &str, typ: SectionType, flags: u64, align: u64) -> Self {
+        Self {
+            section: SectionWrapper::new_with(id, name, typ, flags, align),
+            data: vec![],
+        }
+    }
+    pub fn section_header_mut(&mut self) -> &mut SectionHeader {
+        &mut self.section.elf_header
+    }
+    pub fn set_data(&mut self, data: Vec<u8>) {
+        self.section.elf_header.size = data.len() as u64;
+        self.data = data;
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{
+    linker::{elf_hash, version::VER_FLG_BASE, Verdaux, Verdef, Vernaux, Verneed},
+    utils::{read_struct::write_struct, str_table::StrTableBuilder},
+};
+
+/// The versions required from one shared library, each with the
+/// version index it gets in the output.
+pub struct VersionNeed {
+    pub soname: String,
+    pub versions: Vec<(String, u16)>,
+}
+
+/// A version defined by the output, `parent` is the version it inherits
+/// from in the version script.
+pub struct VersionDef {
+    pub name: String,
+    pub ndx: u16,
+    pub parent: Option<String>,
+}
+
+/// Serialize the .gnu.version section, one entry per dynamic symbol.
+pub fn build_versym(versym: &[u16]) -> Vec<u8> {
+    versym.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+/// Serialize the .gnu.version_r section: a `Verneed` per shared library
+/// immediately followed by its `Vernaux` entries.
+pub fn build_verneed(needs: &[VersionNeed], strtab: &mut StrTableBuilder) -> Vec<u8> {
+    let mut buf = vec![];
+    for (i, need) in needs.iter().enumerate() {
+        let aux_size = size_of::<Vernaux>() * need.versions.len();
+        let verneed = Verneed {
+            version: 1,
+            cnt: need.versions.len() as u16,
+            file: strtab.add(&need.soname),
+            aux: size_of::<Verneed>() as u32,
+            next: if i + 1 == needs.len() {
+                0
+            } else {
+                (size_of::<Verneed>() + aux_size) as u32
+            },
+        };
+        write_struct(&mut buf, &verneed).unwrap();
+        for (j, (name, ndx)) in need.versions.iter().enumerate() {
+            let vernaux = Vernaux {
+                hash: elf_hash(name),
+                flags: 0,
+                other: *ndx,
+                name: strtab.add(name),
+                next: if j + 1 == need.versions.len() {
+                    0
+                } else {
+                    size_of::<Vernaux>() as u32
+                },
+            };
+            write_struct(&mut buf, &vernaux).unwrap();
+        }
+    }
+    buf
+}
+
+/// Serialize the .gnu.version_d section. The first entry is the base
+/// definition named after the output itself, a version inheriting from
+/// another one gets a second `Verdaux` naming its parent.
+pub fn build_verdef(base: &str, defs: &[VersionDef], strtab: &mut StrTableBuilder) -> Vec<u8> {
+    let base = VersionDef {
+        name: base.to_string(),
+        ndx: 1,
+        parent: None,
+    };
+    let defs = std::iter::once(&base).chain(defs).collect::<Vec<_>>();
+    let mut buf = vec![];
+    for (i, def) in defs.iter().enumerate() {
+        let names = std::iter::once(&def.name)
+            .chain(&def.parent)
+            .collect::<Vec<_>>();
+        let verdef = Verdef {
+            version: 1,
+            flags: if def.ndx == 1 { VER_FLG_BASE } else { 0 },
+            ndx: def.ndx,
+            cnt: names.len() as u16,
+            hash: elf_hash(&def.name),
+            aux: size_of::<Verdef>() as u32,
+            next: if i + 1 == defs.len() {
+                0
+            } else {
+                (size_of::<Verdef>() + size_of::<Verdaux>() * names.len()) as u32
+            },
+        };
+        write_struct(&mut buf, &verdef).unwrap();
+        for (j, name) in names.iter().enumerate() {
+            let verdaux = Verdaux {
+                name: strtab.add(name),
+                next: if j + 1 == names.len() {
+                    0
+                } else {
+                    size_of::<Verdaux>() as u32
+                },
+            };
+            write_struct(&mut buf, &verdaux).unwrap();
+        }
+    }
+    buf
+}
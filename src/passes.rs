@@ -1,17 +1,89 @@
-use std::collections::LinkedList;
+use std::{
+    collections::{HashMap, HashSet, LinkedList},
+    rc::Rc,
+    sync::Mutex,
+};
 
-use crate::context::Context;
+use crate::{
+    context::Context,
+    linker::{
+        version::{VER_NDX_GLOBAL, VER_NDX_HIDDEN, VER_NDX_LOCAL},
+        ElfSymbol, SectionFlag, SectionType, SymbolType,
+    },
+    output_section::{
+        copyrel::CopyrelSection,
+        dynsym::DynsymSection,
+        got::{GotPltSection, GotSection},
+        output_section::OutputSection,
+        plt::{IpltSection, PltSection},
+        rela::{DynamicReloc, RelaSection},
+        version::{build_verdef, build_verneed, build_versym, VersionDef, VersionNeed},
+    },
+    symbol::ShareSymbol,
+    utils::str_table::StrTableBuilder,
+    version_script::{VersionScript, Visibility},
+};
 
 impl Context {
     pub fn resolve_symbol(&mut self) {
-        for obj in self.object_iter() {
+        // the first definition wins, so go in command line order
+        for obj in self.objects_in_order() {
             let mut obj = obj.lock().unwrap();
             obj.resolve_symbol();
         }
+        self.resolve_versioned_references();
         println!("before num obj: {}", self.obj_size());
         self.mark_live_objects();
         self.reclaim_objects();
         println!("after num obj: {}", self.obj_size());
+        self.eliminate_duplicate_comdat_groups();
+    }
+
+    /// Keep the first COMDAT group with a given signature, in command line
+    /// order, and drop the members of the other copies. The symbols those
+    /// defined are resolved again, to the kept copy.
+    fn eliminate_duplicate_comdat_groups(&mut self) {
+        let objects = self.objects_in_order();
+        let mut owners: HashMap<String, usize> = HashMap::new();
+        for obj in &objects {
+            let mut obj = obj.lock().unwrap();
+            let id = obj.id;
+            let discarded = obj
+                .comdat_groups
+                .iter()
+                .filter(|(signature, _)| *owners.entry(signature.clone()).or_insert(id) != id)
+                .flat_map(|(_, members)| members.clone())
+                .collect::<Vec<_>>();
+            for shndx in discarded {
+                obj.discard_section(shndx);
+            }
+        }
+        for obj in &objects {
+            obj.lock().unwrap().clear_discarded_definitions();
+        }
+        for obj in &objects {
+            obj.lock().unwrap().resolve_symbol();
+        }
+    }
+
+    /// A reference to foo@V2 is satisfied by the default version foo@@V2,
+    /// which is registered under the unversioned name.
+    fn resolve_versioned_references(&mut self) {
+        let names = self
+            .symbol_names()
+            .into_iter()
+            .filter(|name| name.contains('@'))
+            .collect::<Vec<_>>();
+        for name in names {
+            let (base, version) = name.rsplit_once('@').unwrap();
+            let sym = self.find_symbol_by_name(name.clone());
+            let default = self.find_symbol_by_name(base.to_string());
+            let mut sym = sym.lock().unwrap();
+            let default = default.lock().unwrap();
+            if sym.elf.is_none() && default.version.as_deref() == Some(version) {
+                sym.copy_definition(&default);
+            }
+        }
     }
 
     pub fn mark_live_objects(&self) {
@@ -22,6 +94,21 @@ impl Context {
                 list.push_back(obj.clone());
             }
         }
+        // the members defining the -u symbols are needed
+        for name in self.args.undefined.iter().flatten() {
+            let Some(sym) = self.get_symbol(name) else {
+                continue;
+            };
+            let Some(id) = sym.lock().unwrap().elf else {
+                continue;
+            };
+            let obj = self.get_object(id).unwrap();
+            let mut obj_guard = obj.lock().unwrap();
+            if !obj_guard.is_alive {
+                obj_guard.is_alive = true;
+                list.push_back(obj.clone());
+            }
+        }
 
         assert!(list.len() > 0);
 
@@ -32,3 +119,545 @@ impl Context {
         }
     }
 }
+
+impl Context {
+    /// An executable exports the definitions the shared libraries it links
+    /// refer to, e.g. a callback, so that the dynamic loader finds them.
+    fn export_symbols_referenced_by_dsos(&mut self) {
+        if self.args.is_static() {
+            return;
+        }
+        for obj in self.objects_in_order() {
+            let obj = obj.lock().unwrap();
+            if !obj.is_dso || !obj.is_alive {
+                continue;
+            }
+            let Some(ref info) = obj.symbol_info else {
+                continue;
+            };
+            for (i, sym) in info.global_symbols.iter().enumerate() {
+                if !info.elf_symbols[info.first_global + i].is_undef() {
+                    continue;
+                }
+                let mut sym = sym.lock().unwrap();
+                if sym.elf.is_some() && !sym.is_imported {
+                    sym.is_exported = true;
+                }
+            }
+        }
+    }
+
+    /// Decide which symbols a shared output exports, and their versions,
+    /// given by .symver or the version script. This comes before the
+    /// sections are garbage collected, the exported symbols are roots, and
+    /// before the relocations are scanned, as they can be preempted.
+    pub fn export_symbols(&mut self) {
+        if !self.args.shared {
+            self.export_symbols_referenced_by_dsos();
+            return;
+        }
+        let script = self.version_script();
+        let named_nodes = script.named_nodes();
+        for obj in self.objects_in_order() {
+            let obj = obj.lock().unwrap();
+            if obj.is_dso {
+                continue;
+            }
+            let Some(ref info) = obj.symbol_info else {
+                continue;
+            };
+            for (i, sym) in info.global_symbols.iter().enumerate() {
+                let elf_sym = &info.elf_symbols[info.first_global + i];
+                let mut sym = sym.lock().unwrap();
+                // STV_INTERNAL and STV_HIDDEN
+                if sym.elf != Some(obj.id) || matches!(elf_sym.other & 3, 1 | 2) {
+                    continue;
+                }
+                if let Some(version) = sym.version.clone() {
+                    // the version given by .symver wins over the
+                    // version script, foo@V1 is a non-default version
+                    let ind = named_nodes
+                        .iter()
+                        .position(|node| node.name == version)
+                        .unwrap_or_else(|| {
+                            panic!("version node not found for symbol {}", sym.name)
+                        });
+                    sym.ver_idx = ind as u16 + 2;
+                    if sym.name.contains('@') {
+                        sym.ver_idx |= VER_NDX_HIDDEN;
+                    }
+                } else {
+                    match script.lookup(&sym.name) {
+                        Visibility::Local => continue,
+                        Visibility::Global(ver_idx) => sym.ver_idx = ver_idx,
+                        Visibility::Unspecified => sym.ver_idx = VER_NDX_GLOBAL,
+                    }
+                    sym.version = named_nodes
+                        .get((sym.ver_idx as usize).wrapping_sub(2))
+                        .map(|node| node.name.clone());
+                }
+                sym.is_exported = true;
+                // references to a STV_PROTECTED symbol are bound within
+                // the output
+                sym.is_interposable = elf_sym.other & 3 == 0;
+            }
+        }
+    }
+
+    fn version_script(&self) -> VersionScript {
+        self.args
+            .version_script
+            .as_ref()
+            .map(VersionScript::new)
+            .unwrap_or_default()
+    }
+
+    /// Create the dynamic symbol table along with the symbol versioning
+    /// sections.
+    pub fn create_dynamic_symbols(&mut self) {
+        if self.args.is_static() {
+            return;
+        }
+        let script = self.version_script();
+        let named_nodes = script.named_nodes();
+
+        // symbols imported from shared libraries by live objects, and
+        // when building a shared library, the symbols it exports
+        let mut dynsyms = vec![];
+        let mut seen = HashSet::new();
+        for obj in self.objects_in_order() {
+            let obj = obj.lock().unwrap();
+            if obj.is_dso {
+                continue;
+            }
+            if let Some(ref info) = obj.symbol_info {
+                for (i, sym) in info.global_symbols.iter().enumerate() {
+                    let elf_sym = &info.elf_symbols[info.first_global + i];
+                    let sym_guard = sym.lock().unwrap();
+                    let is_dynamic = if sym_guard.is_imported {
+                        elf_sym.is_undef()
+                    } else {
+                        sym_guard.is_exported && sym_guard.elf == Some(obj.id)
+                    };
+                    if is_dynamic && seen.insert(Rc::as_ptr(sym)) {
+                        dynsyms.push(sym.clone());
+                    }
+                }
+            }
+        }
+        // the copies made by copy relocations are definitions other
+        // shared libraries bind to, this includes the aliases which are
+        // not referenced by the executable itself
+        for sec in [&self.copyrel, &self.copyrel_relro].into_iter().flatten() {
+            for sym in &sec.lock().unwrap().symbols {
+                if seen.insert(Rc::as_ptr(sym)) {
+                    dynsyms.push(sym.clone());
+                }
+            }
+        }
+
+        // assign the version indices of imported symbols, which come
+        // after the ones defined by the output
+        let verdef_num = if self.args.shared && !named_nodes.is_empty() {
+            named_nodes.len() + 1
+        } else {
+            0
+        };
+        let mut needs: Vec<(usize, VersionNeed)> = vec![];
+        let mut next_idx = verdef_num.max(1) as u16 + 1;
+        for sym in &dynsyms {
+            let mut sym = sym.lock().unwrap();
+            if !sym.is_imported {
+                continue;
+            }
+            let (Some(elf), Some(version)) = (sym.elf, sym.version.clone()) else {
+                sym.ver_idx = VER_NDX_GLOBAL;
+                continue;
+            };
+            let ind = match needs.iter().position(|(id, _)| *id == elf) {
+                Some(ind) => ind,
+                None => {
+                    let soname = self.get_object(elf).unwrap().lock().unwrap().soname.clone();
+                    needs.push((
+                        elf,
+                        VersionNeed {
+                            soname,
+                            versions: vec![],
+                        },
+                    ));
+                    needs.len() - 1
+                }
+            };
+            let versions = &mut needs[ind].1.versions;
+            sym.ver_idx = match versions.iter().find(|(name, _)| *name == version) {
+                Some((_, idx)) => *idx,
+                None => {
+                    versions.push((version, next_idx));
+                    next_idx += 1;
+                    next_idx - 1
+                }
+            };
+        }
+        needs.sort_by_key(|(id, _)| *id);
+        let needs = needs.into_iter().map(|(_, need)| need).collect::<Vec<_>>();
+
+        let mut strtab = StrTableBuilder::new();
+        let needed = self
+            .dt_needed()
+            .iter()
+            .map(|soname| strtab.add(soname))
+            .collect();
+        let soname = match self.args.soname {
+            Some(ref soname) if self.args.shared => Some(strtab.add(soname)),
+            _ => None,
+        };
+        let mut versym = vec![VER_NDX_LOCAL];
+        let dynsym = self.add_output_section(DynsymSection::new);
+        for sym in &dynsyms {
+            let elf_sym = {
+                let sym = sym.lock().unwrap();
+                let elf_sym = self.elf_symbol(&sym);
+                // the version is recorded in .gnu.version, not in the name
+                let name = sym.name.split('@').next().unwrap();
+                // an imported IFUNC is resolved by the dynamic loader, to us
+                // it's just a function
+                let info = if sym.is_imported && elf_sym.info & 0xf == SymbolType::GNU_IFUNC as u8 {
+                    (elf_sym.info & 0xf0) | SymbolType::FUNC as u8
+                } else {
+                    elf_sym.info
+                };
+                versym.push(sym.ver_idx);
+                ElfSymbol {
+                    name: strtab.add(name),
+                    info,
+                    other: elf_sym.other,
+                    // the definitions of the output are filled once the
+                    // sections are laid out
+                    shndx: 0,
+                    val: 0,
+                    size: elf_sym.size,
+                }
+            };
+            dynsym.lock().unwrap().add(sym.clone(), elf_sym);
+        }
+        self.dynsym = Some(dynsym);
+
+        let alloc = SectionFlag::ALLOC as u64;
+        if !needs.is_empty() {
+            let data = build_verneed(&needs, &mut strtab);
+            let sec =
+                self.add_synthetic_section(".gnu.version_r", SectionType::GNU_VERNEED, alloc, 8);
+            let mut sec = sec.lock().unwrap();
+            sec.section_header_mut().info = needs.len() as u32;
+            sec.set_data(data);
+        }
+        if verdef_num > 0 {
+            let base = self.args.soname.clone().unwrap_or(self.args.output.clone());
+            let defs = named_nodes
+                .iter()
+                .enumerate()
+                .map(|(i, node)| VersionDef {
+                    name: node.name.clone(),
+                    ndx: i as u16 + 2,
+                    parent: node.parent.clone(),
+                })
+                .collect::<Vec<_>>();
+            let data = build_verdef(&base, &defs, &mut strtab);
+            let sec =
+                self.add_synthetic_section(".gnu.version_d", SectionType::GNU_VERDEF, alloc, 8);
+            let mut sec = sec.lock().unwrap();
+            sec.section_header_mut().info = verdef_num as u32;
+            sec.set_data(data);
+        }
+        if !needs.is_empty() || verdef_num > 0 {
+            let sec = self.add_synthetic_section(".gnu.version", SectionType::GNU_VERSYM, alloc, 2);
+            let mut sec = sec.lock().unwrap();
+            sec.section_header_mut().ent_size = 2;
+            sec.set_data(build_versym(&versym));
+        }
+
+        let dynstr = self.add_synthetic_section(".dynstr", SectionType::STRTAB, alloc, 1);
+        dynstr.lock().unwrap().set_data(strtab.data());
+
+        self.dynsyms = dynsyms;
+        self.create_dynamic_section(needed, soname);
+    }
+
+    /// Scan the relocations of live objects, then create the PLT, GOT
+    /// and copy relocations the symbols imported from shared libraries need.
+    pub fn scan_relocations(&mut self) {
+        let objs = self.objects_in_order();
+        let mut relocs = vec![];
+        for obj in &objs {
+            let obj = obj.lock().unwrap();
+            if obj.is_alive && !obj.is_dso {
+                relocs.extend(obj.scan_relocations(self));
+            }
+        }
+        if !relocs.is_empty() {
+            let rela_dyn = self.rela_dyn();
+            let mut rela_dyn = rela_dyn.lock().unwrap();
+            for reloc in relocs {
+                rela_dyn.add(reloc);
+            }
+        }
+
+        let mut syms: Vec<ShareSymbol> = vec![];
+        let mut seen = HashSet::new();
+        for obj in &objs {
+            let obj = obj.lock().unwrap();
+            if !obj.is_alive || obj.is_dso {
+                continue;
+            }
+            if let Some(ref info) = obj.symbol_info {
+                for sym in info.local_symbols.iter().chain(&info.global_symbols) {
+                    let sym_guard = sym.lock().unwrap();
+                    if (sym_guard.needs_plt
+                        || sym_guard.needs_got
+                        || sym_guard.needs_copyrel
+                        || sym_guard.needs_gottp
+                        || sym_guard.needs_tlsgd
+                        || sym_guard.needs_tlsld)
+                        && seen.insert(Rc::as_ptr(sym))
+                    {
+                        syms.push(sym.clone());
+                    }
+                }
+            }
+        }
+
+        for sym in syms {
+            let sym_guard = sym.lock().unwrap();
+            let (needs_plt, needs_got, needs_copyrel) = (
+                sym_guard.needs_plt,
+                sym_guard.needs_got,
+                sym_guard.needs_copyrel,
+            );
+            let (needs_gottp, needs_tlsgd, needs_tlsld) = (
+                sym_guard.needs_gottp,
+                sym_guard.needs_tlsgd,
+                sym_guard.needs_tlsld,
+            );
+            let (is_iplt, is_imported) = (sym_guard.is_iplt(), sym_guard.is_imported);
+            drop(sym_guard);
+
+            if needs_plt && is_iplt {
+                self.create_iplt(sym.clone());
+            } else if needs_plt {
+                if self.plt.is_none() {
+                    self.plt = Some(self.add_output_section(PltSection::new));
+                    self.got_plt = Some(self.add_output_section(GotPltSection::new));
+                    self.rela_plt =
+                        Some(self.add_output_section(|id| RelaSection::new(id, ".rela.plt")));
+                }
+                let mut plt = self.plt.as_ref().unwrap().lock().unwrap();
+                plt.add_symbol(sym.clone());
+                self.got_plt
+                    .as_ref()
+                    .unwrap()
+                    .lock()
+                    .unwrap()
+                    .set_entries(plt.symbols.len());
+                self.rela_plt
+                    .as_ref()
+                    .unwrap()
+                    .lock()
+                    .unwrap()
+                    .add(DynamicReloc::JumpSlot(sym.clone()));
+            }
+            if needs_got && sym.lock().unwrap().got_idx.is_none() {
+                self.got().lock().unwrap().add_symbol(sym.clone());
+                if let Some(reloc) = self.got_reloc(&sym) {
+                    self.rela_dyn().lock().unwrap().add(reloc);
+                }
+            }
+            if needs_copyrel && sym.lock().unwrap().copyrel.is_none() {
+                self.create_copyrel(sym.clone());
+            }
+            if needs_gottp {
+                self.got().lock().unwrap().add_gottp(sym.clone());
+                // the TLS block of a shared object is placed at runtime
+                if is_imported || self.args.shared {
+                    self.rela_dyn()
+                        .lock()
+                        .unwrap()
+                        .add(DynamicReloc::TpOff(sym.clone()));
+                }
+            }
+            if needs_tlsgd {
+                self.got().lock().unwrap().add_tlsgd(sym.clone());
+                if is_imported {
+                    let rela_dyn = self.rela_dyn();
+                    let mut rela_dyn = rela_dyn.lock().unwrap();
+                    rela_dyn.add(DynamicReloc::DtpMod(sym.clone()));
+                    rela_dyn.add(DynamicReloc::DtpOff(sym.clone()));
+                } else if self.args.shared {
+                    // the module id of a shared object is only known at runtime
+                    self.rela_dyn()
+                        .lock()
+                        .unwrap()
+                        .add(DynamicReloc::DtpMod(sym.clone()));
+                }
+            }
+            if needs_tlsld && self.got().lock().unwrap().tlsld_idx.is_none() {
+                self.got().lock().unwrap().add_tlsld();
+                if self.args.shared {
+                    self.rela_dyn().lock().unwrap().add(DynamicReloc::TlsLd);
+                }
+            }
+        }
+        self.define_iplt_symbols();
+        // _GLOBAL_OFFSET_TABLE_ is the start of .got.plt, or of .got
+        if self.get_symbol("_GLOBAL_OFFSET_TABLE_").is_some() && self.got_plt.is_none() {
+            self.got();
+        }
+    }
+
+    /// An IFUNC defined by the output gets an .iplt entry and a GOT slot
+    /// resolved by an IRELATIVE relocation. Static executables have no
+    /// dynamic loader, crt1.o applies the relocations between
+    /// __rela_iplt_start and __rela_iplt_end itself.
+    fn create_iplt(&mut self, sym: ShareSymbol) {
+        if self.iplt.is_none() {
+            self.iplt = Some(self.add_output_section(IpltSection::new));
+        }
+        let got = self.got();
+        self.iplt
+            .as_ref()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .add_symbol(sym.clone());
+        if sym.lock().unwrap().got_idx.is_none() {
+            got.lock().unwrap().add_symbol(sym.clone());
+        }
+        let rela = if self.args.is_static() {
+            self.rela_iplt()
+        } else {
+            self.rela_dyn()
+        };
+        rela.lock().unwrap().add(DynamicReloc::IRelative(sym));
+    }
+
+    fn rela_iplt(&mut self) -> Rc<Mutex<RelaSection>> {
+        if self.rela_iplt.is_none() {
+            self.rela_iplt = Some(self.add_output_section(|id| RelaSection::new(id, ".rela.iplt")));
+        }
+        self.rela_iplt.clone().unwrap()
+    }
+
+    /// Point __rela_iplt_start and __rela_iplt_end to the boundaries of
+    /// .rela.iplt, they are left undefined in dynamic links.
+    fn define_iplt_symbols(&mut self) {
+        if !self.args.is_static() {
+            return;
+        }
+        let (id, size) = {
+            let rela_iplt = self.rela_iplt();
+            let sec = rela_iplt.lock().unwrap();
+            (sec.id(), sec.section_header().size)
+        };
+        self.define_synthetic_symbol("__rela_iplt_start", id, 0);
+        self.define_synthetic_symbol("__rela_iplt_end", id, size);
+    }
+
+    fn got(&mut self) -> Rc<Mutex<GotSection>> {
+        if self.got.is_none() {
+            self.got = Some(self.add_output_section(GotSection::new));
+        }
+        self.got.clone().unwrap()
+    }
+
+    fn rela_dyn(&mut self) -> Rc<Mutex<RelaSection>> {
+        if self.rela_dyn.is_none() {
+            self.rela_dyn = Some(self.add_output_section(|id| RelaSection::new(id, ".rela.dyn")));
+        }
+        self.rela_dyn.clone().unwrap()
+    }
+
+    /// The dynamic relocation setting the GOT slot of `sym`, if the slot
+    /// isn't filled by the linker: the address of a preemptible symbol is
+    /// only known at runtime, and that of a definition in a position
+    /// independent output moves with its load address.
+    fn got_reloc(&self, sym: &ShareSymbol) -> Option<DynamicReloc> {
+        let sym_guard = sym.lock().unwrap();
+        if sym_guard.is_preemptible() {
+            return Some(DynamicReloc::GlobDat(sym.clone()));
+        }
+        if self.is_pic() && sym_guard.synthetic.is_some() {
+            return Some(DynamicReloc::Relative(sym.clone()));
+        }
+        // undefined weak symbols and absolute ones are 0 everywhere
+        if !self.is_pic()
+            || sym_guard.is_iplt()
+            || sym_guard.elf.is_none()
+            || !sym_guard.is_alive
+            || self.elf_symbol(&sym_guard).is_abs()
+        {
+            return None;
+        }
+        Some(DynamicReloc::Relative(sym.clone()))
+    }
+
+    /// Reserve space for an object of a shared library in the executable.
+    /// Its aliases (e.g. environ and __environ) share the same copy,
+    /// otherwise they would end up pointing to different storage.
+    fn create_copyrel(&mut self, sym: ShareSymbol) {
+        let (dso, elf_sym) = {
+            let sym = sym.lock().unwrap();
+            (
+                self.get_object(sym.elf.unwrap()).unwrap(),
+                self.elf_symbol(&sym),
+            )
+        };
+        let dso = dso.lock().unwrap();
+        let sec = &dso.section_info.elf_sections[elf_sym.shndx as usize];
+        let sec_name = dso.section_info.str_tab.get(sec.name as usize);
+        let is_relro =
+            sec.flags & SectionFlag::WRITE as u64 == 0 || sec_name.starts_with(".data.rel.ro");
+        // the object can't be more aligned than its address in the library
+        let align = if elf_sym.val == 0 {
+            sec.add_align.max(1)
+        } else {
+            sec.add_align.max(1).min(1 << elf_sym.val.trailing_zeros())
+        };
+
+        let copyrel_sec = if is_relro {
+            if self.copyrel_relro.is_none() {
+                self.copyrel_relro =
+                    Some(self.add_output_section(|id| CopyrelSection::new(id, true)));
+            }
+            self.copyrel_relro.clone().unwrap()
+        } else {
+            if self.copyrel.is_none() {
+                self.copyrel = Some(self.add_output_section(|id| CopyrelSection::new(id, false)));
+            }
+            self.copyrel.clone().unwrap()
+        };
+        let offset = copyrel_sec
+            .lock()
+            .unwrap()
+            .reserve(sym.clone(), elf_sym.size, align);
+        sym.lock().unwrap().copyrel = Some((is_relro, offset));
+        self.rela_dyn()
+            .lock()
+            .unwrap()
+            .add(DynamicReloc::Copy(sym.clone()));
+
+        let info = dso.symbol_info.as_ref().unwrap();
+        for (i, alias) in info.global_symbols.iter().enumerate() {
+            let alias_elf = &info.elf_symbols[info.first_global + i];
+            if Rc::ptr_eq(alias, &sym) || alias_elf.is_undef() {
+                continue;
+            }
+            if alias_elf.shndx != elf_sym.shndx || alias_elf.val != elf_sym.val {
+                continue;
+            }
+            let mut alias_guard = alias.lock().unwrap();
+            if alias_guard.elf == Some(dso.id) && alias_guard.copyrel.is_none() {
+                alias_guard.copyrel = Some((is_relro, offset));
+                copyrel_sec.lock().unwrap().symbols.push(alias.clone());
+            }
+        }
+    }
+}
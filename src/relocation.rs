@@ -0,0 +1,664 @@
+use crate::{
+    context::Context,
+    linker::{ElfSymbol, Rela, SectionFlag, SectionIndex, SymbolBinding, SymbolType},
+    output_section::rela::DynamicReloc,
+    symbol::Symbol,
+    utils::input_elf::InputElf,
+};
+
+#[repr(u32)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[allow(non_camel_case_types, unused)]
+pub enum RelType {
+    R_X86_64_NONE = 0,
+    R_X86_64_64 = 1,
+    R_X86_64_PC32 = 2,
+    R_X86_64_GOT32 = 3,
+    R_X86_64_PLT32 = 4,
+    R_X86_64_COPY = 5,
+    R_X86_64_GLOB_DAT = 6,
+    R_X86_64_JUMP_SLOT = 7,
+    R_X86_64_RELATIVE = 8,
+    R_X86_64_GOTPCREL = 9,
+    R_X86_64_32 = 10,
+    R_X86_64_32S = 11,
+    R_X86_64_16 = 12,
+    R_X86_64_PC16 = 13,
+    R_X86_64_8 = 14,
+    R_X86_64_PC8 = 15,
+    R_X86_64_DTPMOD64 = 16,
+    R_X86_64_DTPOFF64 = 17,
+    R_X86_64_TPOFF64 = 18,
+    R_X86_64_TLSGD = 19,
+    R_X86_64_TLSLD = 20,
+    R_X86_64_DTPOFF32 = 21,
+    R_X86_64_GOTTPOFF = 22,
+    R_X86_64_TPOFF32 = 23,
+    R_X86_64_PC64 = 24,
+    R_X86_64_GOTOFF64 = 25,
+    R_X86_64_GOTPC32 = 26,
+    R_X86_64_GOT64 = 27,
+    R_X86_64_GOTPCREL64 = 28,
+    R_X86_64_GOTPC64 = 29,
+    R_X86_64_GOTPLT64 = 30,
+    R_X86_64_PLTOFF64 = 31,
+    R_X86_64_SIZE32 = 32,
+    R_X86_64_SIZE64 = 33,
+    R_X86_64_GOTPC32_TLSDESC = 34,
+    R_X86_64_TLSDESC_CALL = 35,
+    R_X86_64_TLSDESC = 36,
+    R_X86_64_IRELATIVE = 37,
+    R_X86_64_GOTPCRELX = 41,
+    R_X86_64_REX_GOTPCRELX = 42,
+    R_X86_64_CODE_4_GOTPCRELX = 43,
+    R_X86_64_CODE_4_GOTTPOFF = 44,
+    R_X86_64_CODE_4_GOTPC32_TLSDESC = 45,
+}
+
+impl From<u32> for RelType {
+    fn from(value: u32) -> Self {
+        use RelType::*;
+        match value {
+            0 => R_X86_64_NONE,
+            1 => R_X86_64_64,
+            2 => R_X86_64_PC32,
+            3 => R_X86_64_GOT32,
+            4 => R_X86_64_PLT32,
+            5 => R_X86_64_COPY,
+            6 => R_X86_64_GLOB_DAT,
+            7 => R_X86_64_JUMP_SLOT,
+            8 => R_X86_64_RELATIVE,
+            9 => R_X86_64_GOTPCREL,
+            10 => R_X86_64_32,
+            11 => R_X86_64_32S,
+            12 => R_X86_64_16,
+            13 => R_X86_64_PC16,
+            14 => R_X86_64_8,
+            15 => R_X86_64_PC8,
+            16 => R_X86_64_DTPMOD64,
+            17 => R_X86_64_DTPOFF64,
+            18 => R_X86_64_TPOFF64,
+            19 => R_X86_64_TLSGD,
+            20 => R_X86_64_TLSLD,
+            21 => R_X86_64_DTPOFF32,
+            22 => R_X86_64_GOTTPOFF,
+            23 => R_X86_64_TPOFF32,
+            24 => R_X86_64_PC64,
+            25 => R_X86_64_GOTOFF64,
+            26 => R_X86_64_GOTPC32,
+            27 => R_X86_64_GOT64,
+            28 => R_X86_64_GOTPCREL64,
+            29 => R_X86_64_GOTPC64,
+            30 => R_X86_64_GOTPLT64,
+            31 => R_X86_64_PLTOFF64,
+            32 => R_X86_64_SIZE32,
+            33 => R_X86_64_SIZE64,
+            34 => R_X86_64_GOTPC32_TLSDESC,
+            35 => R_X86_64_TLSDESC_CALL,
+            36 => R_X86_64_TLSDESC,
+            37 => R_X86_64_IRELATIVE,
+            41 => R_X86_64_GOTPCRELX,
+            42 => R_X86_64_REX_GOTPCRELX,
+            43 => R_X86_64_CODE_4_GOTPCRELX,
+            44 => R_X86_64_CODE_4_GOTTPOFF,
+            45 => R_X86_64_CODE_4_GOTPC32_TLSDESC,
+            _ => panic!("cannot parse relocation type {}", value),
+        }
+    }
+}
+
+impl InputElf {
+    /// Record what the relocations of this object need from the linker:
+    /// PLT and GOT entries and copy relocations for the symbols imported
+    /// from shared libraries, .iplt entries for IFUNCs, and the GOT
+    /// entries of TLS accesses. Return the dynamic relocations of the
+    /// absolute addresses in a position independent output.
+    pub fn scan_relocations(&self, ctx: &Context) -> Vec<DynamicReloc> {
+        let mut relocs = vec![];
+        let Some(ref info) = self.symbol_info else {
+            return relocs;
+        };
+        for (i, relas) in self.section_info.relocations.iter().enumerate() {
+            let sec = &self.section_info.elf_sections[i];
+            if sec.flags & SectionFlag::ALLOC as u64 == 0
+                || self.is_discarded(i)
+                || self.is_folded(i)
+            {
+                continue;
+            }
+            // the call to __tls_get_addr that follows a relaxed TLS access
+            let mut skip_next = false;
+            for (idx, rela) in relas.iter().enumerate() {
+                if std::mem::take(&mut skip_next) || rela.sym() == 0 {
+                    continue;
+                }
+                let sym = info.symbol(rela.sym());
+                let mut sym = sym.lock().unwrap();
+                let rel_type = RelType::from(rela.typ());
+
+                if sym.elf.is_none() && sym.synthetic.is_none() {
+                    if ctx.args.shared {
+                        // imported from whichever module defines it at
+                        // runtime, through .dynsym
+                        sym.elf = Some(self.id);
+                        sym.index = rela.sym();
+                        sym.is_imported = true;
+                    } else if info.elf_symbols[rela.sym()].bind() != SymbolBinding::WEAK {
+                        panic!("{}: undefined symbol: {}", self.name, sym.name);
+                    }
+                }
+
+                use RelType::*;
+                match rel_type {
+                    // even a load that can be relaxed keeps its GOT slot, it's
+                    // used if the target turns out to be out of reach
+                    R_X86_64_GOTPCREL
+                    | R_X86_64_GOTPCRELX
+                    | R_X86_64_REX_GOTPCRELX
+                    | R_X86_64_GOT64
+                    | R_X86_64_GOTPCREL64 => {
+                        sym.needs_got = true;
+                        continue;
+                    }
+                    R_X86_64_GOTTPOFF => {
+                        sym.needs_gottp = true;
+                        continue;
+                    }
+                    R_X86_64_TLSGD => {
+                        if ctx.relax_tls() {
+                            // relaxed to initial exec for imported symbols,
+                            // to local exec otherwise
+                            sym.needs_gottp |= sym.is_imported;
+                            skip_next = true;
+                        } else {
+                            sym.needs_tlsgd = true;
+                        }
+                        continue;
+                    }
+                    R_X86_64_TLSLD => {
+                        if ctx.relax_tls() {
+                            skip_next = true;
+                        } else {
+                            sym.needs_tlsld = true;
+                        }
+                        continue;
+                    }
+                    // the address doesn't fit in 32 bits once the output
+                    // is loaded anywhere
+                    R_X86_64_32 | R_X86_64_32S if ctx.is_pic() => {
+                        panic!(
+                            "{}: relocation {:?} against {} cannot be used when making a {}, \
+                             recompile with -fPIC",
+                            self.name,
+                            rel_type,
+                            sym.name,
+                            if ctx.args.shared {
+                                "shared object"
+                            } else {
+                                "PIE object"
+                            }
+                        );
+                    }
+                    R_X86_64_TPOFF32 | R_X86_64_TPOFF64 if ctx.args.shared => {
+                        panic!(
+                            "{}: relocation {:?} against {} cannot be used when making a \
+                             shared object, recompile with -fPIC",
+                            self.name, rel_type, sym.name
+                        );
+                    }
+                    _ => {}
+                }
+
+                // defined by the linker, in an output section that moves
+                // with the load address
+                if sym.synthetic.is_some() {
+                    if rel_type == R_X86_64_64 && ctx.is_pic() {
+                        relocs.push(DynamicReloc::Abs64 {
+                            obj: self.id,
+                            shndx: i,
+                            idx,
+                        });
+                    }
+                    continue;
+                }
+                // a weak undefined symbol, or a weak reference to a member
+                // of an archive that isn't pulled in, is 0
+                if sym.elf.is_none() || !sym.is_alive {
+                    continue;
+                }
+                let elf_sym = self.elf_symbol(ctx, &sym);
+                let typ = elf_sym.info & 0xf;
+                let is_ifunc = typ == SymbolType::GNU_IFUNC as u8;
+                // the address moves with the load address of the output, or
+                // is that of a symbol bound at runtime
+                if rel_type == R_X86_64_64
+                    && ctx.is_pic()
+                    && (sym.is_preemptible() || !elf_sym.is_abs())
+                {
+                    relocs.push(DynamicReloc::Abs64 {
+                        obj: self.id,
+                        shndx: i,
+                        idx,
+                    });
+                }
+                if !sym.is_imported {
+                    // an IFUNC is always called and addressed through its
+                    // .iplt entry, whose GOT slot is set by IRELATIVE
+                    if is_ifunc && rel_type != R_X86_64_NONE {
+                        sym.needs_plt = true;
+                    }
+                    continue;
+                }
+                let is_func = typ == SymbolType::FUNC as u8 || is_ifunc;
+
+                match rel_type {
+                    R_X86_64_PLT32 | R_X86_64_PLTOFF64 => sym.needs_plt = true,
+                    R_X86_64_64 if ctx.is_pic() => {}
+                    R_X86_64_64 | R_X86_64_32 | R_X86_64_32S | R_X86_64_PC32 => {
+                        if ctx.args.shared {
+                            panic!(
+                                "{}: relocation {:?} against {} cannot be used when making a \
+                                 shared object, recompile with -fPIC",
+                                self.name, rel_type, sym.name
+                            );
+                        }
+                        if is_func {
+                            // the address of a function is taken by a
+                            // non-PIC executable, make the PLT entry its
+                            // address everywhere so that pointers compare equal
+                            sym.needs_plt = true;
+                            sym.is_canonical = true;
+                        } else {
+                            if ctx.args.has_keyword("nocopyreloc") {
+                                panic!(
+                                    "{}: cannot create a copy relocation for symbol {} \
+                                     (-z nocopyreloc), recompile with -fPIC",
+                                    self.name, sym.name
+                                );
+                            }
+                            sym.needs_copyrel = true;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        relocs
+    }
+
+    /// Apply the relocations of section `shndx` to its contents `buf`,
+    /// which is placed at `addr` in the output.
+    pub fn apply_relocations(&self, ctx: &Context, shndx: usize, buf: &mut [u8], addr: u64) {
+        let relas = &self.section_info.relocations[shndx];
+        self.apply_relocations_at(ctx, shndx, relas, 0, buf, addr);
+    }
+
+    /// Apply `relas`, some relocations of section `shndx`, to the part of
+    /// it that starts at offset `base`. `buf` holds that part, which is
+    /// placed at `addr` in the output.
+    pub fn apply_relocations_at(
+        &self,
+        ctx: &Context,
+        shndx: usize,
+        relas: &[Rela],
+        base: u64,
+        buf: &mut [u8],
+        addr: u64,
+    ) {
+        let Some(ref info) = self.symbol_info else {
+            return;
+        };
+        let tombstone =
+            if self.section_info.elf_sections[shndx].flags & SectionFlag::ALLOC as u64 == 0 {
+                let name = &self.section_info.sections[shndx].as_ref().unwrap().name;
+                ctx.args.dead_reloc_in_nonalloc(name)
+            } else {
+                None
+            };
+        let mut skip_next = false;
+        for (i, rela) in relas.iter().enumerate() {
+            let rel_type = RelType::from(rela.typ());
+            if std::mem::take(&mut skip_next) || rel_type == RelType::R_X86_64_NONE {
+                continue;
+            }
+            let sym = info.symbol(rela.sym());
+            let sym = sym.lock().unwrap();
+            let elf_sym = &info.elf_symbols[rela.sym()];
+            let offset = (rela.offset - base) as usize;
+            // e.g. the debug info of a function removed by --gc-sections
+            if let Some(tombstone) = tombstone {
+                if self.refers_to_discarded(ctx, rela.sym(), &sym) {
+                    let size = match rel_type {
+                        RelType::R_X86_64_64 | RelType::R_X86_64_DTPOFF64 => 8,
+                        _ => 4,
+                    };
+                    buf[offset..offset + size].copy_from_slice(&tombstone.to_le_bytes()[..size]);
+                    continue;
+                }
+            }
+            let p = (addr + offset as u64) as i64;
+            let (s, a) = self.target(ctx, rela, &sym);
+            let got_entry = |idx: Option<usize>| {
+                let got = ctx.got.as_ref().unwrap().lock().unwrap();
+                got.entry_addr(idx.unwrap()) as i64
+            };
+            let (tls_begin, tls_end) = (ctx.tls_begin as i64, ctx.tls_end as i64);
+            let got_base = ctx.got_base() as i64;
+            let size = elf_sym.size as i64;
+
+            // the GOT load stays when the target is out of the reach of %rip
+            let relax_got = matches!(
+                rel_type,
+                RelType::R_X86_64_GOTPCRELX | RelType::R_X86_64_REX_GOTPCRELX
+            ) && i32::try_from(s + a - p).is_ok()
+                && self.can_relax_gotpcrelx(ctx, &sym, rel_type, buf, offset as u64);
+
+            // a 32-bit field must hold the value, in the range of i32 or u32
+            let check = |val: i64, min: i64, max: i64| {
+                if val < min || val > max {
+                    let sec_name = &self.section_info.sections[shndx].as_ref().unwrap().name;
+                    let sym_name = match elf_sym.index() {
+                        SectionIndex::Other(i) if elf_sym.typ() == SymbolType::SECTION => {
+                            self.section_info.sections[i as usize]
+                                .as_ref()
+                                .map_or(String::new(), |sec| sec.name.clone())
+                        }
+                        _ => sym.name.clone(),
+                    };
+                    panic!(
+                        "{}:({}+{:#x}): relocation {:?} against `{}` out of range: \
+                         {} is not in [{}, {}]",
+                        self.name, sec_name, rela.offset, rel_type, sym_name, val, min, max
+                    );
+                }
+                val as u32
+            };
+            // TLSGD and TLSLD are paired with the call to __tls_get_addr
+            let next_type = || match relas.get(i + 1) {
+                Some(next) => RelType::from(next.typ()),
+                None => {
+                    let sec_name = &self.section_info.sections[shndx].as_ref().unwrap().name;
+                    panic!(
+                        "{}:({}+{:#x}): {:?} is not followed by a call to __tls_get_addr",
+                        self.name, sec_name, rela.offset, rel_type
+                    );
+                }
+            };
+            let signed = |val: i64| check(val, i32::MIN as i64, i32::MAX as i64);
+            let unsigned = |val: i64| check(val, 0, u32::MAX as i64);
+            let mut write32 = |val: u32| {
+                buf[offset..offset + 4].copy_from_slice(&val.to_le_bytes());
+            };
+            use RelType::*;
+            match rel_type {
+                R_X86_64_PC32 | R_X86_64_PLT32 => write32(signed(s + a - p)),
+                R_X86_64_32 => write32(unsigned(s + a)),
+                R_X86_64_32S => write32(signed(s + a)),
+                R_X86_64_GOTPCRELX | R_X86_64_REX_GOTPCRELX if relax_got => {
+                    let val = signed(s + a - p);
+                    let insn = relax_gotpcrelx(rel_type, &buf[offset - 3..offset]).unwrap();
+                    buf[offset - 2..offset].copy_from_slice(&insn);
+                    buf[offset..offset + 4].copy_from_slice(&val.to_le_bytes());
+                }
+                R_X86_64_GOTPCREL | R_X86_64_GOTPCRELX | R_X86_64_REX_GOTPCRELX => {
+                    write32(signed(got_entry(sym.got_idx) + a - p))
+                }
+                R_X86_64_GOTPC32 => write32(signed(got_base + a - p)),
+                R_X86_64_GOTTPOFF => write32(signed(got_entry(sym.gottp_idx) + a - p)),
+                R_X86_64_TLSGD if ctx.relax_tls() => {
+                    let next = next_type();
+                    if sym.is_imported {
+                        relax_gd_to_ie(buf, offset, next, got_entry(sym.gottp_idx) - p);
+                    } else {
+                        relax_gd_to_le(buf, offset, next, s - tls_end);
+                    }
+                    skip_next = true;
+                }
+                R_X86_64_TLSLD if ctx.relax_tls() => {
+                    let next = next_type();
+                    relax_ld_to_le(buf, offset, next, tls_end - tls_begin);
+                    skip_next = true;
+                }
+                R_X86_64_TLSGD => write32(signed(got_entry(sym.tlsgd_idx) + a - p)),
+                R_X86_64_TLSLD => {
+                    let got = ctx.got.as_ref().unwrap().lock().unwrap();
+                    write32(signed(
+                        got.entry_addr(got.tlsld_idx.unwrap()) as i64 + a - p,
+                    ))
+                }
+                R_X86_64_TPOFF32 => write32(signed(s + a - tls_end)),
+                R_X86_64_DTPOFF32 => write32(signed(s + a - tls_begin)),
+                R_X86_64_SIZE32 => write32(unsigned(size + a)),
+                _ => {
+                    let val = match rel_type {
+                        R_X86_64_64 => s + a,
+                        R_X86_64_PC64 => s + a - p,
+                        R_X86_64_TPOFF64 => s + a - tls_end,
+                        R_X86_64_DTPOFF64 => s + a - tls_begin,
+                        R_X86_64_SIZE64 => size + a,
+                        // the large code model
+                        R_X86_64_GOTOFF64 | R_X86_64_PLTOFF64 => s + a - got_base,
+                        R_X86_64_GOTPC64 => got_base + a - p,
+                        R_X86_64_GOT64 => got_entry(sym.got_idx) + a - got_base,
+                        R_X86_64_GOTPCREL64 => got_entry(sym.got_idx) + a - p,
+                        _ => panic!("{}: unsupported relocation {:?}", self.name, rel_type),
+                    };
+                    buf[offset..offset + 8].copy_from_slice(&val.to_le_bytes());
+                }
+            }
+        }
+    }
+}
+
+impl InputElf {
+    /// The value S of the symbol of `rela`, resolved to `sym`, and the
+    /// addend A.
+    fn target(&self, ctx: &Context, rela: &Rela, sym: &Symbol) -> (i64, i64) {
+        let elf_sym = &self.symbol_info.as_ref().unwrap().elf_symbols[rela.sym()];
+        match elf_sym.index() {
+            // a section symbol of a mergeable section refers to the
+            // piece at the addend
+            SectionIndex::Other(shndx)
+                if elf_sym.typ() == SymbolType::SECTION
+                    && self.section_info.mergeable_sections[shndx as usize].is_some() =>
+            {
+                let offset = (elf_sym.val as i64 + rela.addend) as u64;
+                (self.section_addr(ctx, shndx as usize, offset) as i64, 0)
+            }
+            _ => (self.symbol_addr(ctx, sym) as i64, rela.addend),
+        }
+    }
+
+    /// The dynamic relocation of the R_X86_64_64 `idx` of section `shndx`
+    /// in a position independent output: against the symbol if it's
+    /// bound at runtime, else relative to the load address.
+    pub fn dynamic_reloc(&self, ctx: &Context, shndx: usize, idx: usize) -> Rela {
+        let rela = &self.section_info.relocations[shndx][idx];
+        let sym = self.symbol_info.as_ref().unwrap().symbol(rela.sym());
+        let sym = sym.lock().unwrap();
+        let place = self.section_addr(ctx, shndx, rela.offset);
+        if sym.is_preemptible() {
+            Rela::new(
+                place,
+                sym.dynsym_idx.unwrap(),
+                RelType::R_X86_64_64 as u32,
+                rela.addend,
+            )
+        } else {
+            let (s, a) = self.target(ctx, rela, &sym);
+            Rela::new(place, 0, RelType::R_X86_64_RELATIVE as u32, s + a)
+        }
+    }
+
+    /// Whether the symbol `idx` of this object, resolved to `sym`, is
+    /// defined in a section that is not copied into the output.
+    fn refers_to_discarded(&self, ctx: &Context, idx: usize, sym: &Symbol) -> bool {
+        let info = self.symbol_info.as_ref().unwrap();
+        if idx < info.first_global {
+            return self.defined_in_discarded(idx);
+        }
+        match sym.elf {
+            Some(id) if id == self.id => self.defined_in_discarded(sym.index),
+            Some(id) if !sym.is_imported => {
+                let obj = ctx.get_object(id).unwrap();
+                let obj = obj.lock().unwrap();
+                obj.defined_in_discarded(sym.index)
+            }
+            _ => false,
+        }
+    }
+
+    fn defined_in_discarded(&self, idx: usize) -> bool {
+        let elf_sym = &self.symbol_info.as_ref().unwrap().elf_symbols[idx];
+        matches!(elf_sym.index(), SectionIndex::Other(i) if self.is_discarded(i as usize))
+    }
+
+    /// The symbol table entry that defines `sym`. This object is locked
+    /// by the caller, so the symbols it defines can't go through ctx.
+    fn elf_symbol(&self, ctx: &Context, sym: &Symbol) -> ElfSymbol {
+        if sym.elf == Some(self.id) {
+            self.symbol_info.as_ref().unwrap().elf_symbols[sym.index].clone()
+        } else {
+            ctx.elf_symbol(sym)
+        }
+    }
+
+    /// Whether the GOT load at `offset` of `data` can refer to `sym`
+    /// directly: it's defined in the output, isn't an IFUNC, isn't an
+    /// absolute symbol that a position independent output can't reach
+    /// relative to %rip, and isn't in a section of the large code model,
+    /// which may be anywhere.
+    fn can_relax_gotpcrelx(
+        &self,
+        ctx: &Context,
+        sym: &Symbol,
+        rel_type: RelType,
+        data: &[u8],
+        offset: u64,
+    ) -> bool {
+        if ctx.args.no_relax || sym.elf.is_none() || !sym.is_alive || sym.is_preemptible() {
+            return false;
+        }
+        let elf_sym = self.elf_symbol(ctx, sym);
+        if elf_sym.info & 0xf == SymbolType::GNU_IFUNC as u8 || (ctx.is_pic() && elf_sym.is_abs()) {
+            return false;
+        }
+        if let SectionIndex::Other(shndx) = elf_sym.index() {
+            let is_large = |obj: &InputElf| {
+                obj.section_info.elf_sections[shndx as usize].flags
+                    & SectionFlag::X86_64_LARGE as u64
+                    != 0
+            };
+            let is_large = if sym.elf == Some(self.id) {
+                is_large(self)
+            } else {
+                is_large(&ctx.get_object(sym.elf.unwrap()).unwrap().lock().unwrap())
+            };
+            if is_large {
+                return false;
+            }
+        }
+        let offset = offset as usize;
+        offset >= 3 && relax_gotpcrelx(rel_type, &data[offset - 3..offset]).is_some()
+    }
+}
+
+/// The opcode and ModRM bytes replacing the two before the relocated
+/// field, given the three bytes before it:
+/// `mov foo@GOTPCREL(%rip), %reg` becomes `lea foo(%rip), %reg`,
+/// `call *foo@GOTPCREL(%rip)` becomes `addr32 call foo` and
+/// `jmp *foo@GOTPCREL(%rip)` becomes `nop; jmp foo`.
+fn relax_gotpcrelx(rel_type: RelType, insn: &[u8]) -> Option<[u8; 2]> {
+    let (op, modrm) = (insn[1], insn[2]);
+    // the mov is rip relative when mod is 00 and r/m is 101
+    if op == 0x8b && modrm & 0xc7 == 0x05 {
+        return Some([0x8d, modrm]);
+    }
+    if rel_type == RelType::R_X86_64_GOTPCRELX {
+        match (op, modrm) {
+            (0xff, 0x15) => return Some([0x67, 0xe8]),
+            (0xff, 0x25) => return Some([0x90, 0xe9]),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Where the code sequence of a TLSGD or TLSLD relocation at `offset`
+/// starts and how long it is. It depends on the call to __tls_get_addr
+/// that follows: through the PLT, through the GOT with -fno-plt, or
+/// `movabs $__tls_get_addr@PLTOFF, %rax; add %reg, %rax; call *%rax` in
+/// the large code model.
+fn tls_sequence(rel_type: RelType, next: RelType, offset: usize) -> (usize, usize) {
+    use RelType::*;
+    // the lea of TLSGD has a 0x66 prefix, except in the large code model
+    match (rel_type, next) {
+        (R_X86_64_TLSGD, R_X86_64_PLT32 | R_X86_64_PC32) => (offset - 4, 16),
+        (R_X86_64_TLSGD, R_X86_64_GOTPCRELX | R_X86_64_GOTPCREL) => (offset - 4, 16),
+        (R_X86_64_TLSLD, R_X86_64_PLT32 | R_X86_64_PC32) => (offset - 3, 12),
+        (R_X86_64_TLSLD, R_X86_64_GOTPCRELX | R_X86_64_GOTPCREL) => (offset - 3, 13),
+        (_, R_X86_64_PLTOFF64) => (offset - 3, 22),
+        _ => panic!("unexpected relocation {:?} after {:?}", next, rel_type),
+    }
+}
+
+/// Write `insn` at the start of `loc`, and fill the rest with a nop.
+fn write_padded(loc: &mut [u8], insn: &[u8]) {
+    const NOPS: [&[u8]; 11] = [
+        &[],
+        &[0x90],
+        &[0x66, 0x90],
+        &[0x0f, 0x1f, 0x00],
+        &[0x0f, 0x1f, 0x40, 0x00],
+        &[0x0f, 0x1f, 0x44, 0x00, 0x00],
+        &[0x66, 0x0f, 0x1f, 0x44, 0x00, 0x00],
+        &[0x0f, 0x1f, 0x80, 0x00, 0x00, 0x00, 0x00],
+        &[0x0f, 0x1f, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00],
+        &[0x66, 0x0f, 0x1f, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00],
+        &[0x66, 0x2e, 0x0f, 0x1f, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00],
+    ];
+    let (head, tail) = loc.split_at_mut(insn.len());
+    head.copy_from_slice(insn);
+    tail.copy_from_slice(NOPS[tail.len()]);
+}
+
+/// `lea x@tlsgd(%rip), %rdi; call __tls_get_addr` becomes
+/// `mov %fs:0, %rax; lea x@tpoff(%rax), %rax`.
+fn relax_gd_to_le(buf: &mut [u8], offset: usize, next: RelType, tpoff: i64) {
+    let (start, len) = tls_sequence(RelType::R_X86_64_TLSGD, next, offset);
+    let mut insn = [
+        0x64, 0x48, 0x8b, 0x04, 0x25, 0, 0, 0, 0, // mov %fs:0, %rax
+        0x48, 0x8d, 0x80, 0, 0, 0, 0, // lea 0(%rax), %rax
+    ];
+    insn[12..16].copy_from_slice(&(tpoff as u32).to_le_bytes());
+    write_padded(&mut buf[start..start + len], &insn);
+}
+
+/// `lea x@tlsgd(%rip), %rdi; call __tls_get_addr` becomes
+/// `mov %fs:0, %rax; add x@gottpoff(%rip), %rax`. `got_disp` is the
+/// distance from the relocated field to the GOT entry.
+fn relax_gd_to_ie(buf: &mut [u8], offset: usize, next: RelType, got_disp: i64) {
+    let (start, len) = tls_sequence(RelType::R_X86_64_TLSGD, next, offset);
+    let mut insn = [
+        0x64, 0x48, 0x8b, 0x04, 0x25, 0, 0, 0, 0, // mov %fs:0, %rax
+        0x48, 0x03, 0x05, 0, 0, 0, 0, // add 0(%rip), %rax
+    ];
+    // relative to the end of the add
+    let disp = got_disp - (start + 16 - offset) as i64;
+    insn[12..16].copy_from_slice(&(disp as u32).to_le_bytes());
+    write_padded(&mut buf[start..start + len], &insn);
+}
+
+/// `lea x@tlsld(%rip), %rdi; call __tls_get_addr` becomes a sequence that
+/// loads the start of the TLS block into %rax, so the DTPOFF32 offsets
+/// that follow stay valid.
+fn relax_ld_to_le(buf: &mut [u8], offset: usize, next: RelType, tls_size: i64) {
+    let (start, len) = tls_sequence(RelType::R_X86_64_TLSLD, next, offset);
+    let mut insn = [
+        0x31, 0xc0, // xor %eax, %eax
+        0x64, 0x48, 0x8b, 0x00, // mov %fs:(%rax), %rax
+        0x48, 0x2d, 0, 0, 0, 0, // sub $tls_size, %rax
+    ];
+    insn[8..12].copy_from_slice(&(tls_size as u32).to_le_bytes());
+    write_padded(&mut buf[start..start + len], &insn);
+}
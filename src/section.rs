@@ -3,12 +3,20 @@ use std::{rc::Rc, sync::Mutex};
 use crate::{context::Context, linker::SectionFlag};
 
 pub type ShareSection = Rc<Mutex<Section>>;
-#[derive(Debug)]
+/// An input section, as (object id, section index).
+pub type SectionId = (usize, usize);
+
+#[derive(Debug, Clone)]
 pub struct Section {
     pub elf: usize,
     pub name: String,
     pub index: usize,
     pub data: Vec<u8>,
+    // the output section this section is copied into, and its offset there
+    pub out_sec: Option<usize>,
+    pub offset: u64,
+    // the identical section output in place of this one, see icf.rs
+    pub folded_into: Option<SectionId>,
 }
 
 impl Section {
@@ -1,6 +1,9 @@
 use std::{rc::Rc, sync::Mutex};
 
-use crate::{output_section::merged_section::ShareSectionFragment, section::Section};
+use crate::{
+    linker::version::VER_NDX_GLOBAL, output_section::merged_section::ShareSectionFragment,
+    section::Section,
+};
 
 pub type ShareSymbol = Rc<Mutex<Symbol>>;
 #[derive(Debug)]
@@ -12,6 +15,44 @@ pub struct Symbol {
     input_section: Option<Section>,
     frag: Option<ShareSectionFragment>,
     pub is_alive: bool,
+    // defined by a shared library
+    pub is_imported: bool,
+    // defined by a COMMON symbol, which any other definition overrides
+    pub is_common: bool,
+    // put into the output .dynsym
+    pub is_exported: bool,
+    // exported by a shared output with the default visibility, so that
+    // another module can provide the definition at runtime
+    pub is_interposable: bool,
+    // the version of an imported symbol, or the version node
+    // --version-script assigns to an exported one
+    pub version: Option<String>,
+    // the output .gnu.version index
+    pub ver_idx: u16,
+    // the index in the output .dynsym
+    pub dynsym_idx: Option<usize>,
+
+    // set by scanning relocations
+    pub needs_plt: bool,
+    pub needs_got: bool,
+    pub needs_copyrel: bool,
+    // TLS accesses through the GOT: initial-exec, general-dynamic and
+    // local-dynamic
+    pub needs_gottp: bool,
+    pub needs_tlsgd: bool,
+    pub needs_tlsld: bool,
+    // the PLT entry is the address of the function in the whole process
+    pub is_canonical: bool,
+    pub plt_idx: Option<usize>,
+    // slot indices in .got
+    pub got_idx: Option<usize>,
+    pub gottp_idx: Option<usize>,
+    pub tlsgd_idx: Option<usize>,
+    // (in the read-only copy section, offset in the copy section)
+    pub copyrel: Option<(bool, usize)>,
+    // defined by the linker at an offset of an output section,
+    // e.g. __rela_iplt_start, (output section id, offset)
+    pub synthetic: Option<(usize, u64)>,
 }
 
 impl Symbol {
@@ -24,8 +65,49 @@ impl Symbol {
             input_section: None,
             frag: None,
             is_alive: true,
+            is_imported: false,
+            is_common: false,
+            is_exported: false,
+            is_interposable: false,
+            version: None,
+            ver_idx: VER_NDX_GLOBAL,
+            dynsym_idx: None,
+            needs_plt: false,
+            needs_got: false,
+            needs_copyrel: false,
+            needs_gottp: false,
+            needs_tlsgd: false,
+            needs_tlsld: false,
+            is_canonical: false,
+            plt_idx: None,
+            got_idx: None,
+            gottp_idx: None,
+            tlsgd_idx: None,
+            copyrel: None,
+            synthetic: None,
         }
     }
+    /// Take over the definition of `other`, used to bind an explicitly
+    /// versioned reference (foo@V2) to the default version (foo@@V2).
+    pub fn copy_definition(&mut self, other: &Symbol) {
+        self.elf = other.elf;
+        self.index = other.index;
+        self.value = other.value;
+        self.input_section = other.input_section.clone();
+        self.frag = other.frag.clone();
+        self.is_imported = other.is_imported;
+        self.is_common = other.is_common;
+        self.version = other.version.clone();
+    }
+    /// Whether references to the symbol are bound by the dynamic loader.
+    pub fn is_preemptible(&self) -> bool {
+        self.is_imported || self.is_interposable
+    }
+    /// Whether the symbol is a local IFUNC which goes through .iplt
+    /// instead of the lazy binding PLT.
+    pub fn is_iplt(&self) -> bool {
+        self.needs_plt && !self.is_imported
+    }
     pub fn set_frag(&mut self, frag: ShareSectionFragment) {
         self.input_section = None;
         self.frag = Some(frag);
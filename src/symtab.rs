@@ -0,0 +1,160 @@
+use crate::{
+    context::Context,
+    linker::{ElfSymbol, SectionIndex, SectionType, SymbolBinding, SymbolType},
+    utils::{input_elf::InputElf, read_struct::write_struct, str_table::StrTableBuilder},
+};
+
+impl Context {
+    /// Create .symtab and .strtab, filled by `write_symtab` once the
+    /// addresses are known, unless -s is given.
+    pub fn create_symtab_sections(&mut self) {
+        if self.args.strip_all {
+            return;
+        }
+        let symtab = self.add_synthetic_section(".symtab", SectionType::SYMTAB, 0, 8);
+        symtab.lock().unwrap().section_header_mut().ent_size = size_of::<ElfSymbol>() as u64;
+        self.symtab = Some(symtab);
+        self.strtab = Some(self.add_synthetic_section(".strtab", SectionType::STRTAB, 0, 1));
+    }
+
+    /// The local symbols of the objects, which -x and -X discard, then
+    /// the global ones, those of hidden visibility turned local as they
+    /// can't be seen from outside the output.
+    pub fn write_symtab(&self) {
+        let (Some(ref symtab), Some(ref strtab)) = (&self.symtab, &self.strtab) else {
+            return;
+        };
+        let mut names = StrTableBuilder::new();
+        let mut locals = vec![ElfSymbol {
+            name: 0,
+            info: 0,
+            other: 0,
+            shndx: 0,
+            val: 0,
+            size: 0,
+        }];
+        let mut globals = vec![];
+        for obj in self.objects_in_order() {
+            let obj = obj.lock().unwrap();
+            if !obj.is_alive || obj.is_dso {
+                continue;
+            }
+            let Some(ref info) = obj.symbol_info else {
+                continue;
+            };
+            for (idx, sym) in info.local_symbols.iter().enumerate().skip(1) {
+                let sym = sym.lock().unwrap();
+                let elf_sym = &info.elf_symbols[idx];
+                // the temporary symbols of the strings and constants
+                // merged across objects are dropped without -X too
+                let merged = matches!(elf_sym.index(), SectionIndex::Other(i)
+                    if obj.section_info.mergeable_sections[i as usize].is_some());
+                if elf_sym.typ() == SymbolType::SECTION
+                    || self.args.discard_all
+                    || ((self.args.discard_locals || merged) && sym.name.starts_with(".L"))
+                {
+                    continue;
+                }
+                if let Some((val, shndx)) = obj.symtab_value(self, idx) {
+                    locals.push(symtab_entry(&mut names, &sym.name, elf_sym, val, shndx));
+                }
+            }
+            for (i, sym) in info.global_symbols.iter().enumerate() {
+                let sym = sym.lock().unwrap();
+                let idx = info.first_global + i;
+                if sym.elf != Some(obj.id) || !sym.is_alive {
+                    continue;
+                }
+                let Some((val, shndx)) = obj.symtab_value(self, idx) else {
+                    continue;
+                };
+                let mut entry =
+                    symtab_entry(&mut names, &sym.name, &info.elf_symbols[idx], val, shndx);
+                // STV_INTERNAL or STV_HIDDEN
+                if matches!(entry.other & 3, 1 | 2) {
+                    entry.info = (SymbolBinding::LOCAL as u8) << 4 | (entry.info & 0xf);
+                    locals.push(entry);
+                } else {
+                    globals.push(entry);
+                }
+            }
+        }
+        // the symbols the linker defines, e.g. _end
+        let mut synthetic = self
+            .symbol_names()
+            .into_iter()
+            .filter_map(|name| {
+                let sym = self.get_symbol(&name)?;
+                let sym = sym.lock().unwrap();
+                sym.synthetic
+                    .filter(|_| sym.elf.is_none())
+                    .map(|def| (name, def))
+            })
+            .collect::<Vec<_>>();
+        synthetic.sort();
+        for (name, (id, offset)) in synthetic {
+            let shndx = self.sections[&id].lock().unwrap().wrapper_mut().shndx as u16;
+            globals.push(ElfSymbol {
+                name: names.add(&name),
+                info: (SymbolBinding::GLOBAL as u8) << 4 | SymbolType::NOTYPE as u8,
+                other: 0,
+                shndx,
+                val: self.section_addrs[&id].wrapping_add(offset),
+                size: 0,
+            });
+        }
+
+        let mut data = vec![];
+        for entry in locals.iter().chain(&globals) {
+            write_struct(&mut data, entry).unwrap();
+        }
+        let mut symtab = symtab.lock().unwrap();
+        symtab.section_header_mut().info = locals.len() as u32;
+        symtab.set_data(data);
+        strtab.lock().unwrap().set_data(names.data());
+        drop(symtab);
+        self.update_non_alloc_offsets();
+    }
+}
+
+impl InputElf {
+    /// The value and the output section index in .symtab and .dynsym of
+    /// the symbol `idx` of this object, None when it's not in the output.
+    pub fn symtab_value(&self, ctx: &Context, idx: usize) -> Option<(u64, u16)> {
+        let elf_sym = &self.symbol_info.as_ref().unwrap().elf_symbols[idx];
+        let SectionIndex::Other(shndx) = elf_sym.index() else {
+            return elf_sym.is_abs().then_some((elf_sym.val, elf_sym.shndx));
+        };
+        let shndx = shndx as usize;
+        let out_shndx = match self.section_info.mergeable_sections.get(shndx) {
+            Some(Some(sec)) => sec.parent.lock().unwrap().wrapper_mut().shndx,
+            _ => {
+                let id = self.section_info.sections[shndx].as_ref()?.out_sec?;
+                ctx.sections[&id].lock().unwrap().wrapper_mut().shndx
+            }
+        };
+        let mut val = self.section_addr(ctx, shndx, elf_sym.val);
+        // relative to the TLS template
+        if elf_sym.typ() == SymbolType::TLS {
+            val -= ctx.tls_begin;
+        }
+        Some((val, out_shndx as u16))
+    }
+}
+
+fn symtab_entry(
+    names: &mut StrTableBuilder,
+    name: &str,
+    elf_sym: &ElfSymbol,
+    val: u64,
+    shndx: u16,
+) -> ElfSymbol {
+    ElfSymbol {
+        name: names.add(name),
+        info: elf_sym.info,
+        other: elf_sym.other,
+        shndx,
+        val,
+        size: elf_sym.size,
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{
+    context::Context,
+    layout::{has_flag, is_tbss},
+    linker::{SectionFlag, SectionHeader, SectionType, SegmentType},
+};
+
+impl Context {
+    /// Define `name` at `offset` in the output section `id`, unless an
+    /// input does. Only the symbols the inputs refer to are defined.
+    pub fn define_synthetic_symbol(&self, name: &str, id: usize, offset: u64) {
+        if let Some(sym) = self.get_symbol(name) {
+            let mut sym = sym.lock().unwrap();
+            if sym.elf.is_none() {
+                sym.synthetic = Some((id, offset));
+            }
+        }
+    }
+
+    /// Mark the symbols defined by the passes below as defined before the
+    /// relocations are scanned, so that the references from a position
+    /// independent output get a RELATIVE relocation. Their address is set
+    /// once the layout is known.
+    pub fn reserve_synthetic_symbols(&self) {
+        let mut names = [
+            "__ehdr_start",
+            "_etext",
+            "etext",
+            "_edata",
+            "edata",
+            "__bss_start",
+            "_end",
+            "end",
+            "_GLOBAL_OFFSET_TABLE_",
+        ]
+        .map(String::from)
+        .to_vec();
+        for name in ["preinit_array", "init_array", "fini_array"] {
+            names.push(format!("__{}_start", name));
+            names.push(format!("__{}_end", name));
+        }
+        if self.args.is_static() {
+            names.push("__rela_iplt_start".to_string());
+            names.push("__rela_iplt_end".to_string());
+        }
+        for obj in self.object_iter() {
+            let obj = obj.lock().unwrap();
+            if !obj.is_alive || obj.is_dso {
+                continue;
+            }
+            for (header, sec) in obj
+                .section_info
+                .elf_sections
+                .iter()
+                .zip(&obj.section_info.sections)
+            {
+                let Some(sec) = sec else {
+                    continue;
+                };
+                if has_flag(header, SectionFlag::ALLOC)
+                    && !has_flag(header, SectionFlag::EXCLUDE)
+                    && is_c_identifier(&sec.name)
+                {
+                    names.push(format!("__start_{}", sec.name));
+                    names.push(format!("__stop_{}", sec.name));
+                }
+            }
+        }
+        for name in names {
+            if let Some(sym) = self.get_symbol(&name) {
+                let mut sym = sym.lock().unwrap();
+                if sym.elf.is_none() && sym.synthetic.is_none() {
+                    // no output section has id 0
+                    sym.synthetic = Some((0, 0));
+                }
+            }
+        }
+    }
+
+    /// Define the symbols which mark the boundaries of the layout, e.g.
+    /// _end for malloc or __ehdr_start for the startup code of static
+    /// executables, and of the output sections.
+    pub fn define_synthetic_symbols(&self) {
+        let sections = self
+            .section_order
+            .iter()
+            .map(|&id| {
+                let sec = self.sections[&id].lock().unwrap();
+                (id, sec.name(), sec.section_header().clone())
+            })
+            .filter(|(_, _, header)| has_flag(header, SectionFlag::ALLOC) && !is_tbss(header))
+            .collect::<Vec<_>>();
+        let is_small = |header: &SectionHeader| !has_flag(header, SectionFlag::X86_64_LARGE);
+        let is_nobits = |header: &SectionHeader| header._type == SectionType::NOBITS;
+        let end = |(id, _, header): &(usize, String, SectionHeader)| (*id, header.size);
+
+        let mut defs = vec![];
+        if let Some((id, _, header)) = sections.first() {
+            // the ELF header is at the start of the first segment, before
+            // any section
+            let first_load = self
+                .phdrs
+                .iter()
+                .find(|phdr| phdr._type == SegmentType::LOAD as u32)
+                .unwrap();
+            let offset = first_load.vaddr.wrapping_sub(header.addr);
+            defs.push(("__ehdr_start", (*id, offset)));
+        }
+        let text = sections
+            .iter()
+            .rfind(|(_, _, header)| is_small(header) && has_flag(header, SectionFlag::EXECINSTR));
+        if let Some(text) = text {
+            defs.push(("_etext", end(text)));
+            defs.push(("etext", end(text)));
+        }
+        let data = sections
+            .iter()
+            .rfind(|(_, _, header)| is_small(header) && !is_nobits(header));
+        if let Some(data) = data {
+            defs.push(("_edata", end(data)));
+            defs.push(("edata", end(data)));
+        }
+        let bss = sections
+            .iter()
+            .find(|(_, _, header)| is_small(header) && is_nobits(header));
+        if let Some((id, _, _)) = bss {
+            defs.push(("__bss_start", (*id, 0)));
+        } else if let Some(data) = data {
+            defs.push(("__bss_start", end(data)));
+        }
+        if let Some(last) = sections.last() {
+            defs.push(("_end", end(last)));
+            defs.push(("end", end(last)));
+        }
+        let got = [".got.plt", ".got"]
+            .iter()
+            .find_map(|name| sections.iter().find(|(_, sec_name, _)| sec_name == name));
+        if let Some((id, _, _)) = got {
+            defs.push(("_GLOBAL_OFFSET_TABLE_", (*id, 0)));
+        }
+
+        for (name, (id, offset)) in defs {
+            self.define_synthetic_symbol(name, id, offset);
+        }
+        self.define_encapsulation_symbols();
+    }
+
+    /// __start_foo and __stop_foo, the boundaries of the output section
+    /// foo, for the sections whose name is a C identifier.
+    fn define_encapsulation_symbols(&self) {
+        for &id in &self.section_order {
+            let sec = self.sections[&id].lock().unwrap();
+            let name = sec.name();
+            if is_c_identifier(&name) {
+                let size = sec.section_header().size;
+                self.define_synthetic_symbol(&format!("__start_{}", name), id, 0);
+                self.define_synthetic_symbol(&format!("__stop_{}", name), id, size);
+            }
+        }
+    }
+}
+
+fn is_c_identifier(name: &str) -> bool {
+    name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && name.chars().next().is_some_and(|c| !c.is_ascii_digit())
+}
@@ -0,0 +1,61 @@
+use std::io::{Cursor, Read, Write};
+
+use crate::linker::{CompressionHeader, ELFCOMPRESS_ZLIB, ELFCOMPRESS_ZSTD};
+
+use super::read_struct::{read_struct, write_struct};
+
+/// Decompress the contents of a SHF_COMPRESSED section, return its
+/// header, which tells the size and alignment of the uncompressed data,
+/// and that data.
+pub fn decompress_section(name: &str, data: &[u8]) -> (CompressionHeader, Vec<u8>) {
+    let mut cursor = Cursor::new(data);
+    let header: CompressionHeader = read_struct(&mut cursor)
+        .unwrap_or_else(|_| panic!("{}: truncated compression header", name));
+    let compressed = &data[cursor.position() as usize..];
+    let mut out = Vec::with_capacity(header.size as usize);
+    let result = match header._type {
+        ELFCOMPRESS_ZLIB => flate2::read::ZlibDecoder::new(compressed).read_to_end(&mut out),
+        ELFCOMPRESS_ZSTD => zstd::stream::read::Decoder::new(compressed)
+            .and_then(|mut decoder| decoder.read_to_end(&mut out)),
+        typ => panic!("{}: unknown compression type {}", name, typ),
+    };
+    if let Err(err) = result {
+        panic!("{}: cannot decompress: {}", name, err);
+    }
+    if out.len() as u64 != header.size {
+        panic!(
+            "{}: decompressed to {} bytes instead of {}",
+            name,
+            out.len(),
+            header.size
+        );
+    }
+    (header, out)
+}
+
+/// Compress the contents of a section of alignment `align` with `typ`,
+/// ELFCOMPRESS_ZLIB or ELFCOMPRESS_ZSTD, into those of a SHF_COMPRESSED
+/// section.
+pub fn compress_section(typ: u32, align: u64, data: &[u8]) -> Vec<u8> {
+    let header = CompressionHeader {
+        _type: typ,
+        reserved: 0,
+        size: data.len() as u64,
+        add_align: align,
+    };
+    let mut out = vec![];
+    write_struct(&mut out, &header).unwrap();
+    match typ {
+        ELFCOMPRESS_ZLIB => {
+            let mut encoder = flate2::write::ZlibEncoder::new(out, flate2::Compression::default());
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        }
+        ELFCOMPRESS_ZSTD => {
+            let mut encoder = zstd::stream::write::Encoder::new(out, 0).unwrap();
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        }
+        _ => unreachable!(),
+    }
+}
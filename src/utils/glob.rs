@@ -0,0 +1,89 @@
+/// Match `s` against a shell style wildcard pattern, as used by version
+/// scripts. `*`, `?` and `[...]` (with `!` or `^` negation) are supported.
+pub fn glob_match(pattern: &str, s: &str) -> bool {
+    let pattern = pattern.as_bytes();
+    let s = s.as_bytes();
+    let (mut p, mut i) = (0, 0);
+    // where to resume when the last `*` has to swallow one more byte
+    let mut backtrack = None;
+    while i < s.len() {
+        if p < pattern.len() {
+            match pattern[p] {
+                b'*' => {
+                    backtrack = Some((p, i));
+                    p += 1;
+                    continue;
+                }
+                b'?' => {
+                    p += 1;
+                    i += 1;
+                    continue;
+                }
+                b'[' => {
+                    if let Some((matched, next)) = match_class(pattern, p, s[i]) {
+                        if matched {
+                            p = next;
+                            i += 1;
+                            continue;
+                        }
+                    } else if s[i] == b'[' {
+                        // an unterminated `[` matches itself
+                        p += 1;
+                        i += 1;
+                        continue;
+                    }
+                }
+                c => {
+                    if c == s[i] {
+                        p += 1;
+                        i += 1;
+                        continue;
+                    }
+                }
+            }
+        }
+        match backtrack {
+            Some((star, pos)) => {
+                p = star + 1;
+                i = pos + 1;
+                backtrack = Some((star, pos + 1));
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Match `c` against the character class starting at `pattern[start]`,
+/// return whether it matches and the position after the class.
+fn match_class(pattern: &[u8], start: usize, c: u8) -> Option<(bool, usize)> {
+    let mut p = start + 1;
+    let negate = p < pattern.len() && (pattern[p] == b'!' || pattern[p] == b'^');
+    if negate {
+        p += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    while p < pattern.len() && (first || pattern[p] != b']') {
+        first = false;
+        if p + 2 < pattern.len() && pattern[p + 1] == b'-' && pattern[p + 2] != b']' {
+            if pattern[p] <= c && c <= pattern[p + 2] {
+                matched = true;
+            }
+            p += 3;
+        } else {
+            if pattern[p] == c {
+                matched = true;
+            }
+            p += 1;
+        }
+    }
+    if p >= pattern.len() {
+        return None;
+    }
+    Some((matched != negate, p + 1))
+}
+
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
@@ -1,15 +1,18 @@
 use core::fmt;
 use std::{
-    fs::File,
     io::{Cursor, Read, Write},
     rc::Rc,
-    str::from_utf8,
     sync::Mutex,
 };
 
 use crate::{
     context::Context,
-    linker::{ElfHeader, ElfSymbol, SectionFlag, SectionHeader, SectionIndex, SectionType},
+    eh_frame::InputEhFrame,
+    linker::{
+        version::{VER_NDX_GLOBAL, VER_NDX_HIDDEN},
+        DynamicEntry, ElfHeader, ElfSymbol, Rela, SectionFlag, SectionHeader, SectionIndex,
+        SectionType, SymbolBinding, Verdaux, Verdef,
+    },
     output_section::{
         merged_section::{FragmentData, ShareSectionFragment},
         output_section::ShareOutputSection,
@@ -18,7 +21,7 @@ use crate::{
     symbol::{self, ShareSymbol, Symbol},
 };
 
-use super::{read_struct::read_struct, str_table::StrTable};
+use super::{compress::decompress_section, read_struct::read_struct, str_table::StrTable};
 
 pub struct InputElf {
     pub name: String,
@@ -26,6 +29,17 @@ pub struct InputElf {
     pub section_info: SectionInfo,
     pub symbol_info: Option<SymbolInfo>,
     pub is_alive: bool,
+    pub is_dso: bool,
+    pub soname: String,
+    // the .gnu.version entry of each dynamic symbol
+    pub versym: Vec<u16>,
+    // version names defined by .gnu.version_d, indexed by version index
+    pub verdefs: Vec<String>,
+    // (signature, member section indices) of the COMDAT groups
+    pub comdat_groups: Vec<(String, Vec<usize>)>,
+    pub eh_frame: Option<InputEhFrame>,
+    // the sections from this index on hold the COMMON symbols
+    pub first_common: usize,
     pub id: usize,
 }
 
@@ -34,9 +48,11 @@ pub struct SectionInfo {
     pub str_tab: StrTable,
     pub sections: Vec<Option<Section>>,
     pub mergeable_sections: Vec<Option<InputMergeableSection>>,
+    // relocations indexed by the section they apply to
+    pub relocations: Vec<Vec<Rela>>,
 }
 
-struct InputMergeableSection {
+pub struct InputMergeableSection {
     pub parent: ShareOutputSection,
     pub fragments: Vec<ShareSectionFragment>,
     pub data: Vec<FragmentData>,
@@ -51,15 +67,14 @@ impl InputMergeableSection {
             offset: vec![],
         }
     }
-    pub fn get_fragment(&self, offset: usize) -> ShareSectionFragment {
-        let mut ind = 0;
-        for (i, o) in self.offset.iter().enumerate() {
-            if *o >= offset {
-                ind = i;
-                break;
-            }
-        }
-        self.fragments[ind].clone()
+    /// The fragment containing `offset` of the input section, and the
+    /// offset within that fragment.
+    pub fn get_fragment(&self, offset: usize) -> (ShareSectionFragment, usize) {
+        let ind = self
+            .offset
+            .partition_point(|&o| o <= offset)
+            .saturating_sub(1);
+        (self.fragments[ind].clone(), offset - self.offset[ind])
     }
 }
 
@@ -67,18 +82,25 @@ pub struct SymbolInfo {
     pub elf_symbols: Vec<ElfSymbol>,
     pub first_global: usize,
     pub str_tab: StrTable,
-    pub local_symbols: Vec<Symbol>,
+    pub local_symbols: Vec<ShareSymbol>,
     pub global_symbols: Vec<ShareSymbol>,
 }
 
-impl SymbolInfo {}
+impl SymbolInfo {
+    /// The symbol with index `idx` in the symbol table.
+    pub fn symbol(&self, idx: usize) -> ShareSymbol {
+        if idx < self.first_global {
+            self.local_symbols[idx].clone()
+        } else {
+            self.global_symbols[idx - self.first_global].clone()
+        }
+    }
+}
 
 impl InputElf {
-    pub fn new(mut file: File, name: String) -> Self {
-        let mut contents = vec![];
-        file.read_to_end(&mut contents).unwrap();
-        Self::new_from_buf(&contents, name)
-    }
+    const ET_DYN: u16 = 3;
+    const DT_SONAME: i64 = 14;
+    const GRP_COMDAT: u32 = 1;
 
     pub fn new_from_buf(data: &[u8], name: String) -> Self {
         let mut cursor = Cursor::new(data);
@@ -94,11 +116,18 @@ impl InputElf {
             section_num = section_header.size;
         }
 
+        // shared libraries export their symbols through .dynsym
+        let is_dso = elf_header._type == Self::ET_DYN;
+        let symbol_table_type = if is_dso {
+            SectionType::DYNSYM
+        } else {
+            SectionType::SYMTAB
+        };
         let mut sections = vec![section_header];
         let mut symbol_table_section = None;
         for _ in 1..section_num {
             let sec: SectionHeader = read_struct(&mut cursor).ok().unwrap();
-            if sec._type == SectionType::SYMTAB {
+            if sec._type == symbol_table_type {
                 symbol_table_section = Some(sec.clone());
             }
             sections.push(sec);
@@ -147,22 +176,126 @@ impl InputElf {
             elf_sections: sections,
             sections: vec![],
             mergeable_sections: vec![],
+            relocations: vec![],
             str_tab: table,
         };
-        for (i, sec) in section_info.elf_sections.iter().enumerate() {
+        let mut soname = name.clone();
+        let mut versym = vec![];
+        let mut verdefs = vec![];
+        for sec in section_info.elf_sections.iter() {
+            if !is_dso {
+                break;
+            }
+            match sec._type {
+                SectionType::DYNAMIC => {
+                    let buf = read_section_data(
+                        &mut cursor,
+                        &section_info.elf_sections[sec.link as usize],
+                    );
+                    let size = buf.len();
+                    let dyn_str = StrTable::new(buf, size);
+                    let mut dyn_cursor = Cursor::new(read_section_data(&mut cursor, sec));
+                    while let Ok(entry) = read_struct::<DynamicEntry, _>(&mut dyn_cursor) {
+                        if entry.tag == Self::DT_SONAME {
+                            soname = dyn_str.get(entry.val as usize);
+                        }
+                    }
+                }
+                SectionType::GNU_VERSYM => {
+                    versym = read_section_data(&mut cursor, sec)
+                        .chunks(2)
+                        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                        .collect();
+                }
+                SectionType::GNU_VERDEF => {
+                    let buf = read_section_data(
+                        &mut cursor,
+                        &section_info.elf_sections[sec.link as usize],
+                    );
+                    let size = buf.len();
+                    let dyn_str = StrTable::new(buf, size);
+                    let mut ver_cursor = Cursor::new(read_section_data(&mut cursor, sec));
+                    let mut offset = 0;
+                    loop {
+                        ver_cursor.set_position(offset);
+                        let verdef: Verdef = read_struct(&mut ver_cursor).unwrap();
+                        ver_cursor.set_position(offset + verdef.aux as u64);
+                        let verdaux: Verdaux = read_struct(&mut ver_cursor).unwrap();
+                        let ndx = verdef.ndx as usize;
+                        if verdefs.len() <= ndx {
+                            verdefs.resize(ndx + 1, "".to_string());
+                        }
+                        verdefs[ndx] = dyn_str.get(verdaux.name as usize);
+                        if verdef.next == 0 {
+                            break;
+                        }
+                        offset += verdef.next as u64;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let mut comdat_groups = vec![];
+        for sec in section_info.elf_sections.iter() {
+            if sec._type != SectionType::GROUP {
+                continue;
+            }
+            // a flag word followed by the indices of the members
+            let words = read_section_data(&mut cursor, sec)
+                .chunks(4)
+                .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                .collect::<Vec<_>>();
+            if words[0] & Self::GRP_COMDAT == 0 {
+                continue;
+            }
+            // the signature is the name of the symbol given by sh_info
+            let info = symbol_info.as_ref().unwrap();
+            let signature = info.elf_symbols[sec.info as usize].name(&info.str_tab);
+            let members = words[1..].iter().map(|&i| i as usize).collect();
+            comdat_groups.push((signature, members));
+        }
+        section_info.relocations = vec![vec![]; section_info.elf_sections.len()];
+        for sec in section_info.elf_sections.iter() {
+            if is_dso || sec._type != SectionType::RELA {
+                continue;
+            }
+            let mut rela_cursor = Cursor::new(read_section_data(&mut cursor, sec));
+            while let Ok(rela) = read_struct::<Rela, _>(&mut rela_cursor) {
+                section_info.relocations[sec.info as usize].push(rela);
+            }
+        }
+        for (i, sec) in section_info.elf_sections.iter_mut().enumerate() {
             use SectionType::*;
             match sec._type {
                 SYMTAB | REL | RELA | STRTAB | NULL => {
                     section_info.sections.push(None);
                 }
                 _ => {
-                    let name = section_info.str_tab.get(sec.name as usize);
-                    let data = read_section_data(&mut cursor, sec);
+                    let sec_name = section_info.str_tab.get(sec.name as usize);
+                    // the size of the section is all there is to NOBITS
+                    let mut data = if sec._type == NOBITS {
+                        vec![]
+                    } else {
+                        read_section_data(&mut cursor, sec)
+                    };
+                    // the rest of the link only sees the uncompressed
+                    // section
+                    if sec.flags & SectionFlag::COMPRESSED as u64 != 0 {
+                        let (chdr, raw) =
+                            decompress_section(&format!("{}:({})", name, sec_name), &data);
+                        data = raw;
+                        sec.size = chdr.size;
+                        sec.add_align = chdr.add_align;
+                        sec.flags &= !(SectionFlag::COMPRESSED as u64);
+                    }
                     let section = Section {
                         elf: 0, // this is a temporary id
-                        name,
+                        name: sec_name,
                         index: i,
                         data,
+                        out_sec: None,
+                        offset: 0,
+                        folded_into: None,
                     };
 
                     section_info.sections.push(Some(section));
@@ -170,13 +303,107 @@ impl InputElf {
             }
         }
 
-        Self {
+        let first_common = section_info.elf_sections.len();
+        let mut elf = Self {
             name,
             elf_header,
             is_alive: false,
+            is_dso,
+            soname,
+            versym,
+            verdefs,
+            comdat_groups,
+            eh_frame: None,
             symbol_info,
             section_info,
+            first_common,
             id: 0,
+        };
+        if !is_dso {
+            elf.allocate_common_symbols();
+        }
+        elf
+    }
+
+    /// Give each COMMON symbol (`int foo;` with -fcommon) a NOBITS section
+    /// of its own in .bss, so that it is resolved like any other
+    /// definition. The value of such a symbol is its alignment.
+    fn allocate_common_symbols(&mut self) {
+        let Some(ref mut info) = self.symbol_info else {
+            return;
+        };
+        for elf_sym in &mut info.elf_symbols[info.first_global..] {
+            if !elf_sym.is_common() {
+                continue;
+            }
+            let shndx = self.section_info.elf_sections.len();
+            assert!(
+                shndx < 0xff00,
+                "{}: too many sections for the COMMON symbols",
+                self.name
+            );
+            self.section_info.elf_sections.push(SectionHeader {
+                name: 0,
+                _type: SectionType::NOBITS,
+                flags: SectionFlag::ALLOC as u64 | SectionFlag::WRITE as u64,
+                addr: 0,
+                offset: 0,
+                size: elf_sym.size,
+                link: 0,
+                info: 0,
+                add_align: elf_sym.val,
+                ent_size: 0,
+            });
+            self.section_info.sections.push(Some(Section {
+                elf: 0,
+                name: ".bss".to_string(),
+                index: shndx,
+                data: vec![],
+                out_sec: None,
+                offset: 0,
+                folded_into: None,
+            }));
+            self.section_info.relocations.push(vec![]);
+            elf_sym.shndx = shndx as u16;
+            elf_sym.val = 0;
+        }
+    }
+    /// The output address of `offset` in the input section `shndx`.
+    pub fn section_addr(&self, ctx: &Context, shndx: usize, offset: u64) -> u64 {
+        if let Some(Some(sec)) = self.section_info.mergeable_sections.get(shndx) {
+            let (frag, delta) = sec.get_fragment(offset as usize);
+            let frag = frag.lock().unwrap();
+            return ctx.section_addrs[&frag.section_id] + frag.offset + delta as u64;
+        }
+        match self.section_info.sections[shndx] {
+            Some(Section {
+                out_sec: Some(id),
+                offset: sec_offset,
+                ..
+            }) => ctx.section_addrs[&id] + sec_offset + offset,
+            // not copied into the output
+            _ => 0,
+        }
+    }
+    /// The address of the symbol with index `idx` defined by this object.
+    pub fn defined_addr(&self, ctx: &Context, idx: usize) -> u64 {
+        let elf_sym = &self.symbol_info.as_ref().unwrap().elf_symbols[idx];
+        match elf_sym.index() {
+            SectionIndex::ABS => elf_sym.val,
+            SectionIndex::UNDEF => 0,
+            SectionIndex::Other(shndx) => self.section_addr(ctx, shndx as usize, elf_sym.val),
+            _ => panic!("{}: unsupported section index of symbol {}", self.name, idx),
+        }
+    }
+    /// Like `Context::symbol_addr`, but usable while this object is locked.
+    pub fn symbol_addr(&self, ctx: &Context, sym: &Symbol) -> u64 {
+        if let Some(addr) = ctx.linker_symbol_addr(sym) {
+            return addr;
+        }
+        if sym.elf == Some(self.id) {
+            self.defined_addr(ctx, sym.index)
+        } else {
+            ctx.definition_addr(sym)
         }
     }
     pub fn find_section(&self, typ: SectionType) -> Option<SectionHeader> {
@@ -192,46 +419,42 @@ impl InputElf {
         let total = self.section_info.elf_sections.len();
         for i in 0..total {
             let elf_sec = &self.section_info.elf_sections[i];
-            if (elf_sec.flags & SectionFlag::MERGE as u64) != 0 {
+            // dropped by create_output_sections
+            let stripped = ctx.args.strips_debug()
+                && self.section_info.sections[i]
+                    .as_ref()
+                    .is_some_and(|sec| sec.name.starts_with(".debug"));
+            if (elf_sec.flags & SectionFlag::MERGE as u64) != 0 && !stripped {
                 if let Some(ref sec) = &self.section_info.sections[i] {
                     let name = sec.name.clone();
                     let typ = elf_sec._type;
                     let flags = elf_sec.flags;
                     let out_sec = ctx.find_mergeable_section(name, typ, flags);
                     let mut mergeable_section = InputMergeableSection::new(out_sec.clone());
-                    let out_sec_guard = out_sec.lock().unwrap();
+                    let mut out_sec_guard = out_sec.lock().unwrap();
                     assert!(out_sec_guard.is_mergeable());
+                    // the output is mergeable too, e.g. by a later link
+                    out_sec_guard.wrapper_mut().elf_header.ent_size = elf_sec.ent_size;
 
                     if (elf_sec.flags & SectionFlag::STRINGS as u64) != 0 {
-                        let mut s_data: Vec<u8> = vec![];
-                        // let mut strings = vec![];
-                        let mut offset = 0;
-                        let size = elf_sec.ent_size as usize;
-                        for chunk in sec.data.chunks(size) {
+                        // split into null terminated strings, a string
+                        // keeps its terminator and is located by its start
+                        let mut start = 0;
+                        let size = (elf_sec.ent_size as usize).max(1);
+                        for (i, chunk) in sec.data.chunks(size).enumerate() {
                             if chunk.iter().all(|&x| x == 0) {
-                                // FIXME: need to handle empty string
-                                let s = {
-                                    // FIXME: how to handle utf8
-                                    if let Ok(s) = from_utf8(&s_data) {
-                                        s.to_string()
-                                    } else {
-                                        let s: String = s_data.iter().map(|&c| c as char).collect();
-                                        s
-                                    }
-                                };
-                                // strings.push(s.clone());
-                                s_data.clear();
-                                mergeable_section.data.push(FragmentData::Str(s));
-                                mergeable_section.offset.push(offset);
-                            } else {
-                                s_data.extend(chunk);
+                                let end = (i + 1) * size;
+                                mergeable_section
+                                    .data
+                                    .push(FragmentData::Str(sec.data[start..end].to_vec()));
+                                mergeable_section.offset.push(start);
+                                start = end;
                             }
-                            offset += size;
                         }
                     } else {
                         // constants
                         let mut offset = 0;
-                        let size = elf_sec.ent_size as usize;
+                        let size = (elf_sec.ent_size as usize).max(1);
                         for chunk in sec.data.chunks(size) {
                             mergeable_section
                                 .data
@@ -262,6 +485,10 @@ impl InputElf {
                 sec.elf = self.id;
             }
         }
+        // sections of a shared library are never copied into the output
+        if self.is_dso {
+            return;
+        }
         self.initialize_mergeable_section(ctx);
 
         // register symbol to output mergeable section
@@ -278,8 +505,8 @@ impl InputElf {
                 assert!(parent.is_mergeable());
                 if let Some(merge) = parent.to_mergeable() {
                     for frag in &sec.data {
-                        // FIXME: is it right to use the ent_size?
-                        let sec_frag = merge.insert(frag, elf.ent_size as usize);
+                        // every piece keeps the alignment of the input section
+                        let sec_frag = merge.insert(frag, elf.add_align as usize);
                         sec.fragments.push(sec_frag);
                     }
                 }
@@ -310,7 +537,7 @@ impl InputElf {
                 //     );
                 // }
                 ///////////////////// DEBUG //////////////////////////
-                let frag = sec.get_fragment(elf.val as usize);
+                let (frag, _) = sec.get_fragment(elf.val as usize);
                 sym.set_frag(frag);
             }
         });
@@ -323,12 +550,12 @@ impl InputElf {
         if let Some(symbol_info) = &mut self.symbol_info {
             for i in 0..symbol_info.first_global {
                 let elf = &symbol_info.elf_symbols[i];
-                let sym = &mut symbol_info.local_symbols[i];
-                f(sym, elf, &self.section_info);
+                let mut sym = symbol_info.local_symbols[i].lock().unwrap();
+                f(&mut sym, elf, &self.section_info);
             }
             for i in symbol_info.first_global..symbol_info.elf_symbols.len() {
                 let ind = i - symbol_info.first_global;
-                let elf = &symbol_info.elf_symbols[ind];
+                let elf = &symbol_info.elf_symbols[i];
                 let sym = symbol_info.global_symbols[ind].clone();
                 let mut sym_guard = sym.lock().unwrap();
                 f(&mut sym_guard, elf, &self.section_info);
@@ -343,18 +570,35 @@ impl InputElf {
                 let elf_sym = &info.elf_symbols[i];
                 let name = elf_sym.name(&info.str_tab);
                 let value = elf_sym.val as usize;
-                info.local_symbols.push(Symbol::new(name, i, value))
+                let mut sym = Symbol::new(name, i, value);
+                sym.elf = Some(self.id);
+                info.local_symbols.push(Rc::new(Mutex::new(sym)));
             }
             for i in global_index..info.elf_symbols.len() {
                 let elf_sym = &info.elf_symbols[i];
-                let name = elf_sym.name(&info.str_tab);
+                let mut name = elf_sym.name(&info.str_tab);
+                // a non-default version (memcpy@GLIBC_2.2.5) can only be
+                // referenced explicitly, so keep it apart from the default one
+                if let Some(&ver) = self.versym.get(i) {
+                    if ver & VER_NDX_HIDDEN != 0 && !elf_sym.is_undef() {
+                        if let Some(version) = self.verdefs.get((ver & !VER_NDX_HIDDEN) as usize) {
+                            name = format!("{}@{}", name, version);
+                        }
+                    }
+                }
+                // likewise `.symver foo, foo@@V2` in a relocatable object
+                // defines the default version of foo, which satisfies
+                // unversioned references, while foo@V1 stays apart
+                if let Some((base, _)) = name.split_once("@@") {
+                    name = base.to_string();
+                }
                 info.global_symbols.push(ctx.find_symbol_by_name(name));
             }
         }
     }
 
     pub fn resolve_symbol(&mut self) {
-        if let Some(ref mut info) = &mut self.symbol_info {
+        if let Some(ref info) = self.symbol_info {
             let start = info.first_global;
             for i in 0..info.global_symbols.len() {
                 let sym = info.global_symbols[i].clone();
@@ -366,11 +610,34 @@ impl InputElf {
                 //     elf_sym.bind()
                 // );
                 match elf_sym.index() {
-                    SectionIndex::Other(_) => {
-                        if sym.elf.is_none() {
+                    SectionIndex::Other(shndx) if self.is_discarded(shndx as usize) => {}
+                    SectionIndex::Other(_) | SectionIndex::ABS => {
+                        let is_common = matches!(elf_sym.index(),
+                            SectionIndex::Other(shndx) if shndx as usize >= self.first_common);
+                        // a definition in a relocatable object takes
+                        // precedence over the one exported by a shared
+                        // library, and any definition over a COMMON one
+                        if sym.elf.is_none()
+                            || (sym.is_imported && !self.is_dso)
+                            || (sym.is_common && !is_common)
+                        {
                             sym.elf = Some(self.id);
+                            sym.is_common = is_common;
                             sym.index = i + start;
                             sym.value = elf_sym.val as usize;
+                            sym.is_imported = self.is_dso;
+                            sym.version = if self.is_dso {
+                                self.versym
+                                    .get(i + start)
+                                    .map(|ver| (ver & !VER_NDX_HIDDEN) as usize)
+                                    .filter(|&ver| ver > VER_NDX_GLOBAL as usize)
+                                    .and_then(|ver| self.verdefs.get(ver).cloned())
+                            } else {
+                                // the version given by .symver, if any
+                                let name = elf_sym.name(&info.str_tab);
+                                name.rsplit_once('@')
+                                    .map(|(_, version)| version.to_string())
+                            };
                         }
                     }
                     _ => {}
@@ -379,6 +646,45 @@ impl InputElf {
         }
     }
 
+    /// Whether the section is dropped from the output, e.g. as a member of
+    /// a duplicate COMDAT group.
+    pub fn is_discarded(&self, shndx: usize) -> bool {
+        self.section_info.sections[shndx].is_none()
+    }
+
+    /// Whether the section is replaced by an identical one, see icf.rs.
+    pub fn is_folded(&self, shndx: usize) -> bool {
+        self.section_info.sections[shndx]
+            .as_ref()
+            .is_some_and(|sec| sec.folded_into.is_some())
+    }
+
+    pub fn discard_section(&mut self, shndx: usize) {
+        self.section_info.sections[shndx] = None;
+        self.section_info.mergeable_sections[shndx] = None;
+    }
+
+    /// Forget the definitions in discarded sections, so that the symbols
+    /// are resolved to another copy.
+    pub fn clear_discarded_definitions(&self) {
+        let Some(ref info) = self.symbol_info else {
+            return;
+        };
+        for (i, sym) in info.global_symbols.iter().enumerate() {
+            let mut sym = sym.lock().unwrap();
+            if sym.elf != Some(self.id) {
+                continue;
+            }
+            if let SectionIndex::Other(shndx) = info.elf_symbols[info.first_global + i].index() {
+                if self.is_discarded(shndx as usize) {
+                    sym.elf = None;
+                    sym.is_imported = false;
+                    sym.version = None;
+                }
+            }
+        }
+    }
+
     pub fn clear_symbol(&self) {
         if let Some(ref info) = &self.symbol_info {
             for i in 0..info.global_symbols.len() {
@@ -394,6 +700,10 @@ impl InputElf {
                 if let Some(id) = sym.elf {
                     if id == self.id {
                         sym.is_alive = false;
+                        // weak references to a library that isn't needed
+                        // stay undefined
+                        sym.is_imported = false;
+                        sym.version = None;
                     }
                 }
             }
@@ -422,6 +732,11 @@ impl InputElf {
                         .expect(&format!("cannot find elf, id: {}", id));
                     let mut elf_guard = elf.lock().unwrap();
 
+                    // weak references, or references from another shared
+                    // library, don't make a shared library needed
+                    if elf_guard.is_dso && (self.is_dso || elf_sym.bind() == SymbolBinding::WEAK) {
+                        continue;
+                    }
                     if elf_sym.index() == SectionIndex::UNDEF && !elf_guard.is_alive {
                         elf_guard.is_alive = true;
                         f(elf.clone());
@@ -0,0 +1,17 @@
+/// Decode the ULEB128 at the start of `data`, return the value and its
+/// length in bytes.
+pub fn read_uleb128(data: &[u8]) -> (u64, usize) {
+    let mut val = 0;
+    for (i, &byte) in data.iter().enumerate() {
+        val |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return (val, i + 1);
+        }
+    }
+    panic!("truncated ULEB128");
+}
+
+/// The length of the LEB128, signed or not, at the start of `data`.
+pub fn leb128_len(data: &[u8]) -> usize {
+    data.iter().position(|&byte| byte & 0x80 == 0).unwrap() + 1
+}
@@ -1,3 +1,6 @@
+pub mod compress;
+pub mod glob;
 pub mod input_elf;
+pub mod leb128;
 pub mod read_struct;
 pub mod str_table;
@@ -1,5 +1,5 @@
 use std::{
-    io::{self, Read},
+    io::{self, Read, Write},
     slice,
 };
 
@@ -17,3 +17,11 @@ pub fn read_struct<T, R: Read>(read: &mut R) -> io::Result<T> {
         }
     }
 }
+
+pub fn write_struct<T, W: Write>(write: &mut W, s: &T) -> io::Result<()> {
+    let num_bytes = ::std::mem::size_of::<T>();
+    unsafe {
+        let buffer = slice::from_raw_parts(s as *const T as *const u8, num_bytes);
+        write.write_all(buffer)
+    }
+}
@@ -1,4 +1,5 @@
 use core::fmt;
+use std::collections::HashMap;
 
 pub struct StrTable {
     data: Vec<u8>,
@@ -39,3 +40,39 @@ impl fmt::Debug for StrTable {
         Ok(())
     }
 }
+
+/// Build a string table for the output, identical strings share one entry.
+pub struct StrTableBuilder {
+    data: Vec<u8>,
+    map: HashMap<String, u32>,
+}
+impl StrTableBuilder {
+    pub fn new() -> Self {
+        Self {
+            data: vec![0],
+            map: HashMap::default(),
+        }
+    }
+    pub fn add(&mut self, s: &str) -> u32 {
+        if s.is_empty() {
+            return 0;
+        }
+        if let Some(&offset) = self.map.get(s) {
+            return offset;
+        }
+        let offset = self.data.len() as u32;
+        self.data.extend(s.as_bytes());
+        self.data.push(0);
+        self.map.insert(s.to_string(), offset);
+        offset
+    }
+    pub fn data(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+}
+
+impl Default for StrTableBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,175 @@
+use std::{fs, path::PathBuf};
+
+use crate::{
+    linker::version::VER_NDX_GLOBAL,
+    utils::glob::{glob_match, is_glob},
+};
+
+/// A version node, e.g. `VERS_2 { global: foo*; local: *; } VERS_1;`.
+/// The anonymous node `{ ... };` has an empty name.
+#[derive(Debug, Default)]
+pub struct VersionNode {
+    pub name: String,
+    pub globals: Vec<String>,
+    pub locals: Vec<String>,
+    pub parent: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct VersionScript {
+    pub nodes: Vec<VersionNode>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Visibility {
+    // exported with the given output version index
+    Global(u16),
+    Local,
+    Unspecified,
+}
+
+impl VersionScript {
+    pub fn new(path: &PathBuf) -> Self {
+        let script = fs::read_to_string(path)
+            .unwrap_or_else(|_| panic!("cannot read version script {:?}", path));
+        Self::parse(&script)
+    }
+
+    pub fn parse(script: &str) -> Self {
+        let tokens = tokenize(script);
+        let mut tokens = tokens.iter().map(|s| s.as_str()).peekable();
+        let mut nodes = vec![];
+        while let Some(token) = tokens.next() {
+            let mut node = VersionNode::default();
+            if token != "{" {
+                node.name = token.to_string();
+                assert_eq!(tokens.next(), Some("{"), "expect '{{' after {}", token);
+            }
+            // patterns before any `global:`/`local:` are global
+            let mut is_global = true;
+            loop {
+                match tokens.next() {
+                    Some("}") => break,
+                    Some("global") if tokens.peek() == Some(&":") => {
+                        tokens.next();
+                        is_global = true;
+                    }
+                    Some("local") if tokens.peek() == Some(&":") => {
+                        tokens.next();
+                        is_global = false;
+                    }
+                    Some("extern") => panic!("extern blocks in version script are not supported"),
+                    Some(pattern) => {
+                        assert_eq!(tokens.next(), Some(";"), "expect ';' after {}", pattern);
+                        if is_global {
+                            node.globals.push(pattern.to_string());
+                        } else {
+                            node.locals.push(pattern.to_string());
+                        }
+                    }
+                    None => panic!("unterminated version node {}", node.name),
+                }
+            }
+            match tokens.next() {
+                Some(";") => {}
+                Some(parent) => {
+                    node.parent = Some(parent.to_string());
+                    assert_eq!(tokens.next(), Some(";"), "expect ';' after {}", parent);
+                }
+                None => panic!("expect ';' after version node {}", node.name),
+            }
+            nodes.push(node);
+        }
+        Self { nodes }
+    }
+
+    /// The named version nodes, which become the version definitions of
+    /// the output. Their version index is the position in this list plus 2,
+    /// index 1 being the base definition.
+    pub fn named_nodes(&self) -> Vec<&VersionNode> {
+        self.nodes.iter().filter(|n| !n.name.is_empty()).collect()
+    }
+
+    /// Decide the visibility of a symbol. Like GNU ld, exact names take
+    /// precedence over wildcards, and a global wildcard over a local one.
+    pub fn lookup(&self, name: &str) -> Visibility {
+        for node in &self.nodes {
+            if node.globals.iter().any(|p| !is_glob(p) && p == name) {
+                return Visibility::Global(self.ver_idx(node));
+            }
+            if node.locals.iter().any(|p| !is_glob(p) && p == name) {
+                return Visibility::Local;
+            }
+        }
+        for node in &self.nodes {
+            if node.globals.iter().any(|p| glob_match(p, name)) {
+                return Visibility::Global(self.ver_idx(node));
+            }
+        }
+        for node in &self.nodes {
+            if node.locals.iter().any(|p| glob_match(p, name)) {
+                return Visibility::Local;
+            }
+        }
+        Visibility::Unspecified
+    }
+
+    fn ver_idx(&self, node: &VersionNode) -> u16 {
+        if node.name.is_empty() {
+            return VER_NDX_GLOBAL;
+        }
+        let ind = self
+            .named_nodes()
+            .iter()
+            .position(|n| n.name == node.name)
+            .unwrap();
+        ind as u16 + 2
+    }
+}
+
+fn tokenize(script: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut chars = script.chars().peekable();
+    let mut cur = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '#' => while chars.next_if(|&c| c != '\n').is_some() {},
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            '"' => {
+                for c in chars.by_ref() {
+                    if c == '"' {
+                        break;
+                    }
+                    cur.push(c);
+                }
+            }
+            '{' | '}' | ';' | ':' => {
+                if !cur.is_empty() {
+                    tokens.push(cur.clone());
+                    cur.clear();
+                }
+                tokens.push(c.to_string());
+            }
+            c if c.is_whitespace() => {
+                if !cur.is_empty() {
+                    tokens.push(cur.clone());
+                    cur.clear();
+                }
+            }
+            c => cur.push(c),
+        }
+    }
+    if !cur.is_empty() {
+        tokens.push(cur);
+    }
+    tokens
+}
@@ -0,0 +1,31 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+cat <<EOF | gcc -o "$t"/a.o -c -xc -
+#include <stdio.h>
+
+int main(void) {
+    printf("Hello %d\n", 42);
+    return 0;
+}
+EOF
+
+needed() {
+    readelf -dW "$1" | grep '(NEEDED)' | sed 's/.*\[\(.*\)\]/\1/' | xargs
+}
+
+# libc.so and libm.so are linker scripts naming the libraries
+gcc -B. -fno-lto -no-pie "$t"/a.o -o "$t"/as_needed -Wl,--as-needed -lm
+[ "$("$t"/as_needed)" = "Hello 42" ] || exit 1
+[ "$(needed "$t"/as_needed)" = "libc.so.6" ] || exit 1
+
+gcc -B. -fno-lto -no-pie "$t"/a.o -o "$t"/no_as_needed -Wl,--no-as-needed -lm
+[ "$("$t"/no_as_needed)" = "Hello 42" ] || exit 1
+[ "$(needed "$t"/no_as_needed)" = "libm.so.6 libc.so.6" ] || exit 1
+
+readelf -lW "$t"/as_needed | grep -q 'Requesting program interpreter: /lib64/ld-linux-x86-64.so.2' || exit 1
+readelf -lW "$t"/as_needed | grep -q '^ *DYNAMIC '
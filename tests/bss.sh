@@ -0,0 +1,62 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+cat <<EOF | gcc -o "$t"/a.o -c -xc -
+#include <stdio.h>
+
+static char big[64 << 20];
+__attribute__((section(".bss.rel.ro"))) int relro_bss;
+int data = 42;
+
+int main() {
+    big[sizeof(big) - 1] = 1;
+    printf("%d %d %d %d\n", big[0], big[sizeof(big) - 1], relro_bss, data);
+    return 0;
+}
+EOF
+
+gcc -B. -fno-lto -static "$t"/a.o -o "$t"/out
+
+# the bss takes no room in the file
+[ $(stat -c %s "$t"/out) -lt $((16 << 20)) ] || exit 1
+read -r _ _ _ _ filesz memsz _ < <(readelf -lW "$t"/out | grep ' LOAD ' | tail -1)
+[ $((memsz - filesz)) -ge $((64 << 20)) ] || exit 1
+
+# and comes after the data of its segment, but for the relro bss which
+# is made read-only with the rest of PT_GNU_RELRO
+addr() {
+    readelf -SW "$t"/out | sed 's/^ *\[ *[0-9]*\]//' | grep "^ $1 " | awk '{ print $3 }'
+}
+[ $((0x$(addr .bss) > 0x$(addr .data))) = 1 ] || exit 1
+read -r _ _ relro _ relro_size _ < <(readelf -lW "$t"/out | grep ' GNU_RELRO ')
+[ $((0x$(addr .bss.rel.ro) >= relro && 0x$(addr .bss.rel.ro) < relro + relro_size)) = 1 ] || exit 1
+[ $((0x$(addr .data) >= relro + relro_size)) = 1 ] || exit 1
+
+[ "$("$t"/out)" = "0 1 0 42" ] || exit 1
+
+# tentative definitions become COMMON symbols, allocated in .bss unless
+# another object defines them
+cat <<EOF2 | gcc -o "$t"/b.o -c -xc -fcommon -
+#include <stdio.h>
+
+int counter;
+int table[1000];
+double aligned __attribute__((aligned(64)));
+
+int main() {
+    table[999] = 3;
+    printf("%d %d %d\n", counter, table[999], (int)((unsigned long)&aligned % 64));
+    return 0;
+}
+EOF2
+cat <<EOF2 | gcc -o "$t"/c.o -c -xc -fcommon -
+int counter = 7;
+int table[1000];
+EOF2
+
+gcc -B. -fno-lto -static "$t"/b.o "$t"/c.o -o "$t"/common
+[ "$("$t"/common)" = "7 3 0" ]
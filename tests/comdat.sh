@@ -0,0 +1,54 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+cat <<EOF | g++ -o "$t"/a.o -c -xc++ -fno-exceptions -
+#include <stdio.h>
+
+inline int counter() {
+    static int n;
+    return ++n;
+}
+int other();
+
+int main() {
+    int first = counter();
+    printf("%d %d\n", first, other());
+    return 0;
+}
+EOF
+cat <<EOF | g++ -o "$t"/b.o -c -xc++ -fno-exceptions -
+inline int counter() {
+    static int n;
+    return ++n;
+}
+
+int other() {
+    return counter();
+}
+EOF
+
+gcc -B. -fno-lto -static "$t"/a.o "$t"/b.o -o "$t"/out || exit 1
+[ "$("$t"/out)" = "1 2" ] || exit 1
+
+# the strings of a dropped copy of a group don't make it to the output
+cat <<EOF | gcc -o "$t"/c.o -c -xassembler -
+.section .rodata.str1.1,"aMSG",@progbits,1,grp,comdat
+.string "kept copy"
+.text
+.globl main
+main:
+    xorl %eax, %eax
+    ret
+EOF
+cat <<EOF | gcc -o "$t"/d.o -c -xassembler -
+.section .rodata.str1.1,"aMSG",@progbits,1,grp,comdat
+.string "dropped copy"
+EOF
+
+gcc -B. -fno-lto -static "$t"/c.o "$t"/d.o -o "$t"/strings
+grep -q 'kept copy' "$t"/strings || exit 1
+! grep -q 'dropped copy' "$t"/strings
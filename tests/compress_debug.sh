@@ -0,0 +1,34 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+# enough debug info for the compression to pay off
+{
+    echo '#include <stdio.h>'
+    echo 'int debug_var = 42;'
+    for i in $(seq 200); do
+        echo "int debug_func_$i(int arg_$i) { int local_$i = arg_$i * $i; return local_$i; }"
+    done
+    echo 'int main() { printf("%d\n", debug_var); return 0; }'
+} | gcc -o "$t"/a.o -c -xc -g -
+
+gcc -B. -fno-lto -static "$t"/a.o -o "$t"/plain
+[ "$(readelf -SW "$t"/plain | grep -c ' \.debug_.* C ')" = 0 ] || exit 1
+
+for mode in zlib zstd; do
+    gcc -B. -fno-lto -static "$t"/a.o -Wl,--compress-debug-sections=$mode -o "$t"/$mode
+    [ "$("$t"/$mode)" = 42 ] || exit 1
+    readelf -SW "$t"/$mode | grep ' \.debug_info ' | grep -q ' C ' || exit 1
+    readelf -tW "$t"/$mode | grep -qi "$mode" || exit 1
+    [ $(stat -c %s "$t"/$mode) -lt $(stat -c %s "$t"/plain) ] || exit 1
+    # the debug info is the same once decompressed
+    readelf --debug-dump=info "$t"/$mode > "$t"/$mode.info 2>&1
+    ! grep -qi 'warning' "$t"/$mode.info || exit 1
+    grep -q 'DW_AT_name.*debug_var' "$t"/$mode.info || exit 1
+done
+
+readelf --debug-dump=info "$t"/plain | sed 1d > "$t"/plain.info
+sed 1d "$t"/zstd.info | cmp -s - "$t"/plain.info
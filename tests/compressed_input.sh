@@ -0,0 +1,32 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+cat <<EOF | gcc -o "$t"/a.o -c -xc -g -gz=zlib -
+int zlib_var = 1;
+int zstd_func(void);
+
+int main(void) {
+    return zlib_var + zstd_func();
+}
+EOF
+cat <<EOF | gcc -o "$t"/b.o -c -xc -g -
+int zstd_var = 2;
+
+int zstd_func(void) {
+    return zstd_var;
+}
+EOF
+objcopy --compress-debug-sections=zstd "$t"/b.o "$t"/b.o
+readelf -SW "$t"/a.o | grep ' \.debug_info ' | grep -q ' C ' || exit 1
+readelf -SW "$t"/b.o | grep ' \.debug_info ' | grep -q ' C ' || exit 1
+
+gcc -B. -fno-lto -static "$t"/a.o "$t"/b.o -o "$t"/out
+! readelf -SW "$t"/out | grep ' \.debug_info ' | grep -q ' C ' || exit 1
+readelf --debug-dump=info "$t"/out > "$t"/info 2>&1
+! grep -qi 'warning' "$t"/info || exit 1
+grep -q 'DW_AT_name.*zlib_var' "$t"/info || exit 1
+grep -q 'DW_AT_name.*zstd_var' "$t"/info
@@ -0,0 +1,62 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+cat <<EOF > "$t"/a.c
+#include <stdio.h>
+#include <string.h>
+
+extern char **environ;
+static char buf[64];
+
+int main(void) {
+    for (char **e = environ; *e; e++)
+        if (!strncmp(*e, "FOO=", 4))
+            strcpy(buf, *e + 4);
+    fprintf(stdout, "%s\n", buf);
+    return 0;
+}
+EOF
+
+# stdout and environ are copied into .bss, next to buf
+gcc -fno-PIC -o "$t"/a.o -c "$t"/a.c
+gcc -B. -fno-lto -no-pie "$t"/a.o -o "$t"/out
+[ "$(FOO=bar "$t"/out)" = "bar" ] || exit 1
+readelf -rW "$t"/out | grep -q 'R_X86_64_COPY .* stdout' || exit 1
+readelf -rW "$t"/out | grep -q 'R_X86_64_COPY .* environ' || exit 1
+[ "$(readelf -SW "$t"/out | grep -c ' \.bss ')" = 1 ] || exit 1
+
+# code going through the GOT doesn't need copy relocations
+gcc -fPIC -o "$t"/b.o -c "$t"/a.c
+gcc -B. -fno-lto -no-pie "$t"/b.o -o "$t"/nocopyreloc -Wl,-z,nocopyreloc
+[ "$(FOO=baz "$t"/nocopyreloc)" = "baz" ] || exit 1
+! readelf -rW "$t"/nocopyreloc | grep -q R_X86_64_COPY || exit 1
+
+! gcc -B. -fno-lto -no-pie "$t"/a.o -o "$t"/fail -Wl,-z,nocopyreloc 2> "$t"/log || exit 1
+grep -q 'cannot create a copy relocation for symbol' "$t"/log || exit 1
+
+# a read-only object is copied into .data.rel.ro, which is made read-only
+# again once relocated
+cat <<EOF2 | gcc -fPIC -o "$t"/c.o -c -xc -
+const int answer = 42;
+EOF2
+cat <<EOF2 | gcc -fno-PIC -o "$t"/d.o -c -xc -
+#include <stdio.h>
+
+extern const int answer;
+
+int main(void) {
+    printf("%d\n", answer);
+    return 0;
+}
+EOF2
+
+gcc -B. -fno-lto -shared "$t"/c.o -o "$t"/libanswer.so
+gcc -B. -fno-lto -no-pie "$t"/d.o -o "$t"/relro -L"$t" -lanswer
+[ "$(LD_LIBRARY_PATH="$t" "$t"/relro)" = "42" ] || exit 1
+read -r _ _ addr _ < <(readelf -SW "$t"/relro | sed 's/^ *\[ *[0-9]*\]//' | grep '^ \.data\.rel\.ro ')
+read -r _ _ relro _ relro_size _ < <(readelf -lW "$t"/relro | grep ' GNU_RELRO ')
+[ $((0x$addr >= relro && 0x$addr < relro + relro_size)) = 1 ]
@@ -0,0 +1,39 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+cat <<EOF | gcc -o "$t"/a.o -c -xc -g -gdwarf-4 -ffunction-sections -
+#include <stdio.h>
+
+int unused_func(int x) {
+    return x * 3;
+}
+
+int main() {
+    printf("%d\n", 42);
+    return 0;
+}
+EOF
+
+# the low_pc of a function
+low_pc() {
+    grep -A6 "DW_AT_name.*: $2\$" "$1" | grep DW_AT_low_pc | awk '{ print $NF }'
+}
+
+gcc -B. -fno-lto -static "$t"/a.o -Wl,--gc-sections -o "$t"/out
+[ "$("$t"/out)" = 42 ] || exit 1
+readelf --debug-dump=info "$t"/out > "$t"/info 2>&1
+[ "$(low_pc "$t"/info main)" != 0 ] || exit 1
+[ "$(low_pc "$t"/info unused_func)" = 0 ] || exit 1
+# a zero pair would end the range list of the unit, and a -1 begin
+# would change its base address
+readelf -x .debug_ranges "$t"/out | grep -q '01000000 00000000 01000000 00000000' || exit 1
+
+gcc -B. -fno-lto -static "$t"/a.o -Wl,--gc-sections \
+    -Wl,-z,dead-reloc-in-nonalloc='.debug_i*=0xdeadbeef' -o "$t"/out2
+readelf --debug-dump=info "$t"/out2 > "$t"/info2 2>&1
+[ "$(low_pc "$t"/info2 unused_func)" = 0xdeadbeef ] || exit 1
+[ "$(low_pc "$t"/info2 main)" = "$(low_pc "$t"/info main)" ]
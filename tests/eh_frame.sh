@@ -0,0 +1,45 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+cat <<EOF | g++ -o "$t"/a.o -c -xc++ -ffunction-sections -
+#include <stdio.h>
+
+inline int twice(int x) {
+    if (x < 0)
+        throw x;
+    return x * 2;
+}
+int other(int);
+int unused(int x) { return twice(x) + 1; }
+
+int main() {
+    try {
+        other(-1);
+    } catch (int e) {
+        printf("%d %d\n", e, twice(3));
+    }
+    return 0;
+}
+EOF
+cat <<EOF | g++ -o "$t"/b.o -c -xc++ -ffunction-sections -
+inline int twice(int x) {
+    if (x < 0)
+        throw x;
+    return x * 2;
+}
+
+int other(int x) {
+    return twice(x);
+}
+EOF
+
+# the FDEs of the dropped copy of twice and of the collected unused
+# would describe the code at address 0
+gcc -B. -fno-lto -static "$t"/a.o "$t"/b.o -lstdc++ -Wl,--gc-sections -o "$t"/out
+readelf -SW "$t"/out | grep -q ' \.eh_frame ' || exit 1
+readelf --debug-dump=frames "$t"/out | grep -q 'FDE' || exit 1
+! readelf --debug-dump=frames "$t"/out | grep -q 'pc=0000000000000000'
@@ -0,0 +1,30 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+cat <<EOF | g++ -o "$t"/a.o -c -xc++ -
+#include <stdio.h>
+
+int f(int x) {
+    if (x < 0)
+        throw x;
+    return x;
+}
+
+int main() {
+    try {
+        f(-1);
+    } catch (int e) {
+        printf("%d\n", e);
+    }
+    return 0;
+}
+EOF
+
+gcc -B. -fno-lto -static "$t"/a.o -lstdc++ -Wl,--eh-frame-hdr -o "$t"/out
+readelf -SW "$t"/out | grep -q ' \.eh_frame_hdr ' || exit 1
+readelf -lW "$t"/out | grep -q 'GNU_EH_FRAME' || exit 1
+! readelf --debug-dump=frames "$t"/out 2>&1 | grep -qi 'warning'
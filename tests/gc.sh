@@ -0,0 +1,61 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+cat <<EOF | gcc -o "$t"/a.o -c -xc -ffunction-sections -fdata-sections -
+#include <stdio.h>
+
+__attribute__((section("gc_unused"))) int unused = 1;
+__attribute__((section("gc_start"))) int started = 2;
+__attribute__((section("gc_retain"), retain)) int retained = 3;
+extern int __start_gc_start[];
+
+int unused_fn(void) { return unused; }
+
+int main() {
+    printf("%d\n", __start_gc_start[0]);
+    return 0;
+}
+EOF
+
+gcc -B. -fno-lto -static "$t"/a.o -Wl,--gc-sections -o "$t"/out
+readelf -SW "$t"/out | grep -q ' gc_start ' || exit 1
+readelf -SW "$t"/out | grep -q ' gc_retain ' || exit 1
+! readelf -SW "$t"/out | grep -q ' gc_unused ' || exit 1
+
+gcc -B. -fno-lto -static "$t"/a.o -Wl,--gc-sections -Wl,-u,unused_fn -o "$t"/out
+readelf -SW "$t"/out | grep -q ' gc_unused '
+
+gcc -B. -fno-lto -static "$t"/a.o -Wl,--gc-sections,--print-gc-sections,--why-live=started \
+    -o "$t"/out > "$t"/log
+grep -q 'removing unused section .*a.o:(gc_unused)' "$t"/log || exit 1
+grep -q 'live symbol: started in .*a.o:(gc_start)' "$t"/log || exit 1
+grep -q '>>> referenced by .*a.o:(.text.main)' "$t"/log || exit 1
+
+# a function only a shared library calls is kept in the executable
+cat <<EOF2 | gcc -o "$t"/b.o -c -xc -fPIC -
+int callback(void);
+
+int call(void) {
+    return callback() + 1;
+}
+EOF2
+cat <<EOF2 | gcc -o "$t"/c.o -c -xc -ffunction-sections -
+#include <stdio.h>
+
+int call(void);
+int callback(void) { return 41; }
+
+int main() {
+    printf("%d\n", call());
+    return 0;
+}
+EOF2
+
+gcc -B. -fno-lto -shared "$t"/b.o -o "$t"/libcall.so
+gcc -B. -fno-lto "$t"/c.o -Wl,--gc-sections -o "$t"/dynamic -L"$t" -lcall
+readelf -W --dyn-syms "$t"/dynamic | grep -Eq ' [0-9]+ callback$' || exit 1
+[ "$(LD_LIBRARY_PATH="$t" "$t"/dynamic)" = "42" ]
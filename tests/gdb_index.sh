@@ -0,0 +1,57 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+cat <<EOF | gcc -o "$t"/a.o -c -xc -g -ggnu-pubnames -
+#include <stdio.h>
+
+struct point {
+    int x, y;
+};
+
+struct point origin;
+int helper(int);
+
+int main() {
+    printf("%d\n", helper(origin.x));
+    return 0;
+}
+EOF
+cat <<EOF | gcc -o "$t"/b.o -c -xc -g -ggnu-pubnames -
+static int twice(int x) {
+    return 2 * x;
+}
+
+int helper(int x) {
+    return twice(x) + 42;
+}
+EOF
+
+gcc -B. -fno-lto -static "$t"/a.o "$t"/b.o -Wl,--gdb-index -o "$t"/out
+[ "$("$t"/out)" = 42 ] || exit 1
+# the index replaces the public names
+! readelf -SW "$t"/out | grep -q '\.debug_gnu_pub' || exit 1
+
+readelf --debug-dump=gdb_index "$t"/out > "$t"/index 2>&1
+grep -q '^Version 7$' "$t"/index || exit 1
+sed -n '/^CU table:/,/^$/p' "$t"/index | grep -c '^\[' | grep -qx 2 || exit 1
+sed -n '/^Address table:/,/^$/p' "$t"/index | grep -c ' [01]$' | grep -qx 2 || exit 1
+grep -q '\] main: 0 \[global, function\]' "$t"/index || exit 1
+grep -q '\] helper: 1 \[global, function\]' "$t"/index || exit 1
+grep -q '\] twice: 1 \[static, function\]' "$t"/index || exit 1
+grep -q '\] origin: 0 \[global, variable\]' "$t"/index || exit 1
+grep -q '\] point: 0 \[static, type\]' "$t"/index || exit 1
+
+# gdb looks the names up by their hash in a table whose size is the
+# distance from the symbol table to the constant pool
+objcopy --dump-section .gdb_index="$t"/index.bin "$t"/out "$t"/out.tmp
+read -r _ _ _ _ table pool < <(od -An -tu4 -N24 "$t"/index.bin | xargs)
+mask=$(((pool - table) / 8 - 1))
+hash=0
+for c in $(echo -n main | od -An -tu1); do
+    hash=$(((hash * 67 + c - 113) & 0xffffffff))
+done
+grep -q "^\[ *$((hash & mask))\] main:" "$t"/index
@@ -0,0 +1,31 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+cat <<EOF | gcc -o "$t"/a.o -c -xc -O2 -ffunction-sections -
+#include <stdio.h>
+
+__attribute__((noinline)) int f1(int x) { return x * 3 + 1; }
+__attribute__((noinline)) int f2(int x) { return x * 3 + 1; }
+__attribute__((noinline)) int g1(int x) { return x * 5 + 2; }
+__attribute__((noinline)) int g2(int x) { return x * 5 + 2; }
+
+int (*volatile fp)(int) = g2;
+
+int main() {
+    printf("%d %d %d %d\n", f1(1), f2(2), g1(3), fp(4));
+    return 0;
+}
+EOF
+
+gcc -B. -fno-lto -static "$t"/a.o -Wl,--icf=all,--print-icf-sections -o "$t"/out > "$t"/log
+grep -qE 'removing identical section .*a.o:\(.text.f[12]\)' "$t"/log || exit 1
+grep -qE 'removing identical section .*a.o:\(.text.g[12]\)' "$t"/log || exit 1
+
+# the address of g2 is taken
+gcc -B. -fno-lto -static "$t"/a.o -Wl,--icf=safe,--print-icf-sections -o "$t"/out > "$t"/log
+grep -qE 'removing identical section .*a.o:\(.text.f[12]\)' "$t"/log || exit 1
+! grep -qE 'removing identical section .*a.o:\(.text.g[12]\)' "$t"/log
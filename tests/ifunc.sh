@@ -0,0 +1,33 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+cat <<EOF | gcc -o "$t"/a.o -c -xc -
+#include <stdio.h>
+
+static int foo_impl(void) { return 42; }
+
+// called by __libc_start_main through IRELATIVE before main
+static int (*resolve_foo(void))(void) { return foo_impl; }
+int foo(void) __attribute__((ifunc("resolve_foo")));
+
+int (*pfoo)(void) = foo;
+
+int main(void) {
+    printf("%d %d %d\n", foo(), pfoo(), pfoo == foo);
+    return 0;
+}
+EOF
+
+gcc -B. -fno-lto -static "$t"/a.o -o "$t"/out
+readelf -rW "$t"/out | grep -q R_X86_64_IRELATIVE || exit 1
+
+# the startup code applies the relocations between these symbols
+read -r _ _ addr _ size _ < <(readelf -SW "$t"/out | sed 's/^ *\[ *[0-9]*\]//' | grep '^ \.rela\.iplt ')
+start=$(nm "$t"/out | sed -n 's/ R __rela_iplt_start$//p')
+end=$(nm "$t"/out | sed -n 's/ R __rela_iplt_end$//p')
+[ $((0x$start)) = $((0x$addr)) ] && [ $((0x$end)) = $((0x$addr + 0x$size)) ] || exit 1
+[ "$("$t"/out)" = "42 42 1" ]
@@ -0,0 +1,60 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+cat <<EOF | gcc -o "$t"/a.o -c -xc -
+#include <stdio.h>
+
+__attribute__((constructor(200))) void c200(void) { puts("200"); }
+__attribute__((constructor(101))) void c101(void) { puts("101"); }
+__attribute__((constructor)) void cdef(void) { puts("default"); }
+void ctor1(void);
+void ctor2(void);
+void p100(void);
+
+extern void (*__init_array_start[])(void);
+extern void (*__init_array_end[])(void);
+
+// what .init_array should hold after the entry of crtbegin.o
+__attribute__((section("expected"), used))
+void *expected_order[] = {p100, c101, c200, cdef, ctor1, ctor2};
+__attribute__((section("bounds"), used))
+void *init_array_bounds[] = {__init_array_start, __init_array_end};
+
+int main() {
+    return 0;
+}
+EOF
+cat <<EOF | gcc -o "$t"/b.o -c -xc -
+#include <stdio.h>
+
+void ctor1(void) { puts("ctor1"); }
+void ctor2(void) { puts("ctor2"); }
+void p100(void) { puts("100"); }
+
+// run from the end to the start
+__attribute__((section(".ctors"), used, aligned(8))) static void (*ctors[])(void) = {ctor2, ctor1};
+__attribute__((section(".ctors.65435"), used)) static void (*ctors100[])(void) = {p100};
+EOF
+
+gcc -B. -fno-lto -static "$t"/a.o "$t"/b.o -o "$t"/out
+! readelf -SW "$t"/out | grep -q ' \.ctors ' || exit 1
+
+dump() {
+    objcopy -O binary --only-section="$1" "$t"/out "$t"/"$1".bin
+    od -An -tx8 -v "$t"/"$1".bin | tr -s ' ' '\n' | grep .
+}
+dump .init_array | grep -Fxf <(dump expected) > "$t"/actual
+diff "$t"/actual <(dump expected) || exit 1
+
+read -r _ _ addr _ size _ < <(readelf -SW "$t"/out | sed 's/^ *\[ *[0-9]*\]//' | grep '^ \.init_array ')
+[ "$(dump bounds | xargs)" = "$(printf '%016x %016x' 0x$addr $((0x$addr + 0x$size)))" ] || exit 1
+
+# there is no .preinit_array, so its bounds are the end of .text
+read -r _ _ addr _ size _ < <(readelf -SW "$t"/out | sed 's/^ *\[ *[0-9]*\]//' | grep '^ \.text ')
+start=$(nm "$t"/out | sed -n 's/ [a-zA-Z] __preinit_array_start$//p')
+end=$(nm "$t"/out | sed -n 's/ [a-zA-Z] __preinit_array_end$//p')
+[ $((0x$start)) = $((0x$addr + 0x$size)) ] && [ $((0x$end)) = $((0x$start)) ]
@@ -0,0 +1,23 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+cat <<EOF | gcc -o "$t"/a.o -c -xc -mcmodel=medium -
+#include <stdio.h>
+
+char big[1 << 20];
+char data[1 << 20] = {1};
+
+int main(void) {
+    big[(1 << 20) - 1] = data[0];
+    printf("%d\n", big[(1 << 20) - 1]);
+    return 0;
+}
+EOF
+
+
+gcc -B. -fno-lto -static "$t"/a.o -o "$t"/out
+readelf -SW "$t"/out | grep -q ' \.lbss '
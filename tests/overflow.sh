@@ -0,0 +1,62 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+cat <<EOF | gcc -o "$t"/far.o -c -xassembler -
+.globl far
+.set far, 0x123456789
+EOF
+
+# R_X86_64_32 is zero extended
+cat <<EOF | gcc -o "$t"/a.o -c -xassembler -
+.globl main
+main:
+    movl \$far, %eax
+    ret
+EOF
+
+# R_X86_64_32S is sign extended
+cat <<EOF | gcc -o "$t"/b.o -c -xassembler -
+.globl main
+main:
+    movq \$far - 0x200000000, %rax
+    ret
+EOF
+
+# the bounds themselves fit
+cat <<EOF | gcc -o "$t"/c.o -c -xassembler -
+.globl max32, min32s
+.set max32, 0xffffffff
+.set min32s, -0x80000000
+.globl get32, get32s
+get32:
+    movl \$max32, %eax
+    ret
+get32s:
+    movq \$min32s, %rax
+    ret
+EOF
+
+cat <<EOF | gcc -o "$t"/d.o -c -xc -
+#include <stdio.h>
+
+unsigned long get32(void);
+long get32s(void);
+
+int main(void) {
+    printf("%lu %ld\n", get32(), get32s());
+    return 0;
+}
+EOF
+
+gcc -B. -fno-lto -static "$t"/c.o "$t"/d.o -o "$t"/out
+[ "$("$t"/out)" = "4294967295 -2147483648" ] || exit 1
+
+! gcc -B. -fno-lto -static "$t"/a.o "$t"/far.o -o "$t"/a 2> "$t"/log || exit 1
+grep -qF 'a.o:(.text+0x1): relocation R_X86_64_32 against `far` out of range: 4886718345 is not in [0, 4294967295]' "$t"/log || exit 1
+
+! gcc -B. -fno-lto -static "$t"/b.o "$t"/far.o -o "$t"/b 2> "$t"/log || exit 1
+grep -qF 'relocation R_X86_64_32S against `far` out of range: -3703216247 is not in [-2147483648, 2147483647]' "$t"/log
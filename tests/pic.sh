@@ -0,0 +1,59 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+cat <<EOF | gcc -fPIC -o "$t"/foo.o -c -xc -
+#include <stdio.h>
+
+static int local = 2;
+int *plocal = &local;
+static const char *name = "foo";
+
+void hello(int n) { printf("%s %d\n", name, n + *plocal); }
+EOF
+
+cat <<EOF | gcc -fPIE -o "$t"/a.o -c -xc -
+#include <stdio.h>
+
+void hello(int n);
+static int x = 40;
+int *px = &x;
+void (*fn)(int) = hello;
+
+int main(void) {
+    fn(*px);
+    printf("%s\n", stdout ? "stdout" : "none");
+    return 0;
+}
+EOF
+
+# the pointers in .data move with the load address
+gcc -B. -fno-lto -shared "$t"/foo.o -o "$t"/libfoo.so
+readelf -rW "$t"/libfoo.so | grep -q R_X86_64_RELATIVE || exit 1
+gcc -B. -fno-lto -pie "$t"/a.o -o "$t"/out -L"$t" -lfoo
+readelf -hW "$t"/out | grep -q 'DYN (' || exit 1
+readelf -rW "$t"/out | grep -q R_X86_64_RELATIVE || exit 1
+[ "$(LD_LIBRARY_PATH="$t" "$t"/out | xargs)" = "foo 42 stdout" ] || exit 1
+
+# a 32-bit absolute address can't be relocated at load time, even that
+# of a symbol defined in the output
+cat <<EOF2 | gcc -o "$t"/b.o -c -xassembler -
+.data
+.globl counter
+.hidden counter
+counter:
+    .long 0
+.text
+.globl main
+main:
+    movl \$counter, %eax
+    ret
+EOF2
+
+! gcc -B. -fno-lto -pie "$t"/b.o -o "$t"/fail 2> "$t"/log || exit 1
+grep -qF 'relocation R_X86_64_32 against counter cannot be used when making a PIE object, recompile with -fPIC' "$t"/log || exit 1
+! gcc -B. -fno-lto -shared "$t"/b.o -o "$t"/fail.so 2> "$t"/log || exit 1
+grep -qF 'cannot be used when making a shared object, recompile with -fPIC' "$t"/log
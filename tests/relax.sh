@@ -0,0 +1,90 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+# counter may be interposed, so only the load of local_counter is relaxed
+cat <<EOF | gcc -o "$t"/foo.o -c -xassembler -Wa,-mrelax-relocations=yes -
+.data
+.globl counter
+.type counter, @object
+.size counter, 4
+counter:
+    .long 42
+.globl local_counter
+.hidden local_counter
+local_counter:
+    .long 0
+
+.text
+.globl get
+get:
+    movq counter@GOTPCREL(%rip), %rax
+    movl (%rax), %eax
+    movq local_counter@GOTPCREL(%rip), %rcx
+    addl (%rcx), %eax
+    ret
+EOF
+
+cat <<EOF | gcc -fno-PIC -o "$t"/a.o -c -xc -
+#include <stdio.h>
+
+extern int counter;
+int get(void);
+
+int main(void) {
+    counter++;
+    printf("%d\n", get());
+    return 0;
+}
+EOF
+
+gcc -B. -fno-lto -shared "$t"/foo.o -o "$t"/libfoo.so
+objdump -d --no-show-raw-insn "$t"/libfoo.so > "$t"/log
+grep -Eq 'mov +0x[0-9a-f]+\(%rip\),%rax' "$t"/log || exit 1
+grep -Eq 'lea +0x[0-9a-f]+\(%rip\),%rcx' "$t"/log || exit 1
+
+# the executable has a copy of counter that the library must use
+gcc -B. -fno-lto -no-pie "$t"/a.o -o "$t"/out -L"$t" -lfoo
+readelf -rW "$t"/out | grep -q 'R_X86_64_COPY .* counter' || exit 1
+[ "$(LD_LIBRARY_PATH="$t" "$t"/out)" = "43" ] || exit 1
+
+# a load whose target is out of the reach of %rip stays a GOT load
+cat <<EOF2 | gcc -o "$t"/abs.o -c -xassembler -
+.globl distant
+.set distant, 0x123456789
+EOF2
+cat <<EOF2 | gcc -o "$t"/far.o -c -xassembler -Wa,-mrelax-relocations=yes -
+.section .lbss,"awl",@nobits
+    .zero 0x90000000
+.globl huge
+huge:
+    .zero 8
+
+.text
+.globl get_distant, get_huge
+get_distant:
+    movq distant@GOTPCREL(%rip), %rax
+    ret
+# the sections of the large code model may be anywhere
+get_huge:
+    movq huge@GOTPCREL(%rip), %rax
+    ret
+EOF2
+cat <<EOF2 | gcc -o "$t"/b.o -c -xc -
+#include <stdio.h>
+
+void *get_distant(void);
+
+int main(void) {
+    printf("%p\n", get_distant());
+    return 0;
+}
+EOF2
+
+gcc -B. -fno-lto -no-pie "$t"/b.o "$t"/far.o "$t"/abs.o -o "$t"/far
+objdump -d --no-show-raw-insn "$t"/far | grep -A1 -E '<get_(distant|huge)>:' > "$t"/log
+[ "$(grep -Ec 'mov +0x[0-9a-f]+\(%rip\),%rax' "$t"/log)" = 2 ] || exit 1
+[ "$("$t"/far)" = "0x123456789" ]
@@ -0,0 +1,59 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+cat <<EOF | gcc -o "$t"/a.o -c -xc -ffunction-sections -fdata-sections -
+#include <stdio.h>
+
+struct plugin {
+    const char *name;
+};
+
+extern struct plugin __start_registry[], __stop_registry[];
+
+__attribute__((section("registry"), used))
+static struct plugin alpha = {"alpha"};
+
+int main() {
+    for (struct plugin *p = __start_registry; p < __stop_registry; p++)
+        printf("%s\n", p->name);
+    return 0;
+}
+EOF
+cat <<EOF | gcc -o "$t"/b.o -c -xc -ffunction-sections -fdata-sections -
+struct plugin {
+    const char *name;
+};
+
+// nothing refers to it but the loop over the section
+__attribute__((section("registry"), used))
+static struct plugin beta = {"beta"};
+EOF
+
+# the descriptors are kept by the references to __start_ and __stop_
+gcc -B. -fno-lto -static "$t"/a.o "$t"/b.o -Wl,--gc-sections -o "$t"/out
+[ "$("$t"/out | xargs)" = "alpha beta" ] || exit 1
+
+# the addresses of the boundaries are relocated in a position independent
+# executable
+cat <<EOF2 | gcc -o "$t"/c.o -c -xc -fPIE -
+#include <stdio.h>
+
+extern int __start_ids[], __stop_ids[];
+
+__attribute__((section("ids"), used)) static int id_list[] = {3, 4};
+
+int *start = __start_ids;
+int *stop = __stop_ids;
+
+int main() {
+    printf("%d %d\n", *start, (int)(stop - start));
+    return 0;
+}
+EOF2
+
+gcc -B. -fno-lto -pie "$t"/c.o -o "$t"/pie
+[ "$("$t"/pie)" = "3 2" ]
@@ -0,0 +1,59 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+# -L keeps the .L labels of gcc, e.g. .LFB0 at the start of a function
+cat <<EOF | gcc -o "$t"/a.o -c -xc -g -Wa,-L -
+#include <stdio.h>
+
+__thread int tls_var = 5;
+
+static int twice(int x) {
+    return 2 * x;
+}
+
+int main() {
+    printf("%d\n", twice(21) + tls_var - 5);
+    return 0;
+}
+EOF
+
+symbols() {
+    readelf -sW "$1" | awk '$8 != "" { print $5, $8 }'
+}
+
+gcc -B. -fno-lto -static "$t"/a.o -o "$t"/out
+[ "$("$t"/out)" = 42 ] || exit 1
+symbols "$t"/out > "$t"/syms
+grep -qx 'GLOBAL main' "$t"/syms || exit 1
+grep -qx 'LOCAL twice' "$t"/syms || exit 1
+grep -q '^LOCAL \.LFB' "$t"/syms || exit 1
+readelf -SW "$t"/out | grep -q ' \.debug_info ' || exit 1
+# the value of a TLS symbol is its offset in the TLS template
+[ $((0x$(nm "$t"/out | grep ' tls_var$' | cut -d' ' -f1) < 0x1000)) = 1 ] || exit 1
+
+# -X drops the .L symbols, -x all the local ones
+gcc -B. -fno-lto -static "$t"/a.o -Wl,-X -o "$t"/out-X
+symbols "$t"/out-X > "$t"/syms-X
+! grep -q ' \.L' "$t"/syms-X || exit 1
+grep -qx 'LOCAL twice' "$t"/syms-X || exit 1
+
+gcc -B. -fno-lto -static "$t"/a.o -Wl,-x -o "$t"/out-x
+symbols "$t"/out-x > "$t"/syms-x
+! grep -qx 'LOCAL twice' "$t"/syms-x || exit 1
+grep -qx 'GLOBAL main' "$t"/syms-x || exit 1
+
+# -S drops the debug sections, -s the symbol table too
+gcc -B. -fno-lto -static "$t"/a.o -Wl,-S -o "$t"/out-S
+[ "$("$t"/out-S)" = 42 ] || exit 1
+! readelf -SW "$t"/out-S | grep -q ' \.debug_' || exit 1
+readelf -SW "$t"/out-S | grep -q ' \.symtab ' || exit 1
+
+gcc -B. -fno-lto -static "$t"/a.o -Wl,-s -o "$t"/out-s
+[ "$("$t"/out-s)" = 42 ] || exit 1
+! readelf -SW "$t"/out-s | grep -q ' \.debug_' || exit 1
+! readelf -SW "$t"/out-s | grep -qE ' \.(symtab|strtab) ' || exit 1
+[ $(stat -c %s "$t"/out-s) -lt $(stat -c %s "$t"/out-S) ]
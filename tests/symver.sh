@@ -0,0 +1,47 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+# libfoo.so has an old foo@VER_1 and a default foo@@VER_2
+cat <<EOF | gcc -fPIC -o "$t"/foo.o -c -xc -
+int foo1(void) { return 1; }
+int foo2(void) { return 2; }
+__asm__(".symver foo1, foo@VER_1");
+__asm__(".symver foo2, foo@@VER_2");
+EOF
+
+cat <<EOF > "$t"/foo.ver
+VER_1 { global: foo; local: *; };
+VER_2 { global: foo; } VER_1;
+EOF
+
+gcc -shared "$t"/foo.o -o "$t"/libfoo.so -Wl,--version-script="$t"/foo.ver -Wl,-soname,libfoo.so
+
+cat <<EOF | gcc -o "$t"/a.o -c -xc -
+#include <stdio.h>
+
+int foo_old(void);
+__asm__(".symver foo_old, foo@VER_1");
+int foo(void);
+
+int main(void) {
+    printf("%d %d\n", foo_old(), foo());
+    return 0;
+}
+EOF
+
+gcc -B. -fno-lto -no-pie "$t"/a.o -o "$t"/out -L"$t" -lfoo
+[ "$(LD_LIBRARY_PATH="$t" "$t"/out)" = "1 2" ] || exit 1
+
+readelf -VW "$t"/out > "$t"/log
+grep -q 'File: libfoo.so  Cnt: 2' "$t"/log || exit 1
+grep -q 'Name: VER_1' "$t"/log || exit 1
+grep -q 'Name: VER_2' "$t"/log || exit 1
+
+# the definitions named by .symver when the library is linked by us
+mkdir -p "$t"/ours
+gcc -B. -fno-lto -shared "$t"/foo.o -o "$t"/ours/libfoo.so -Wl,--version-script="$t"/foo.ver
+[ "$(LD_LIBRARY_PATH="$t"/ours "$t"/out)" = "1 2" ]
@@ -0,0 +1,61 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+cat <<EOF | gcc -o "$t"/a.o -c -xc -
+extern char __ehdr_start[], _etext[], _edata[], __bss_start[], _end[];
+extern char _GLOBAL_OFFSET_TABLE_[];
+
+int zero;
+
+__attribute__((section("addrs"), used))
+void *synthetic_addrs[] = {__ehdr_start, _etext, _edata, __bss_start, _end, _GLOBAL_OFFSET_TABLE_};
+
+int main() {
+    return zero;
+}
+EOF
+
+gcc -B. -fno-lto -static "$t"/a.o -o "$t"/out
+
+objcopy -O binary --only-section=addrs "$t"/out "$t"/addrs.bin
+read -r ehdr etext edata bss_start end got < <(od -An -tx8 -v "$t"/addrs.bin | xargs)
+[ -n "$got" ] || exit 1
+
+# the address and the end of a section
+section() {
+    read -r _ _ addr _ size _ < <(readelf -SW "$t"/out | sed 's/^ *\[ *[0-9]*\]//' | grep "^ $1 ")
+    [ -n "$addr" ] && printf '%016x %016x\n' 0x$addr $((0x$addr + 0x$size))
+}
+
+read -r _ _ load _ < <(readelf -lW "$t"/out | grep ' LOAD ')
+[ "$ehdr" = "$(printf '%016x' $load)" ] || exit 1
+[ "$etext" = "$(section __libc_freeres_fn | cut -d' ' -f2)" ] || exit 1
+[ "$bss_start" = "$(section .bss | cut -d' ' -f1)" ] || exit 1
+[ $((0x$edata <= 0x$bss_start)) = 1 ] || exit 1
+[ "$end" = "$(section __libc_freeres_ptrs | cut -d' ' -f2)" ] || exit 1
+# the assembler turns a reference to _GLOBAL_OFFSET_TABLE_ into its
+# distance from the reference
+addrs=$(section addrs | cut -d' ' -f1)
+[ "$got" = "$(printf '%016x' $((0x$(section .got | cut -d' ' -f1) - (0x$addrs + 40))))" ] || exit 1
+
+# a pointer to _end in a position independent executable is relocated
+# at load time
+cat <<EOF2 | gcc -o "$t"/b.o -c -xc -fPIE -
+#include <stdio.h>
+
+extern char _end[];
+char *p = _end;
+
+int main() {
+    printf("%d\n", p == _end);
+    return 0;
+}
+EOF2
+
+gcc -B. -fno-lto -pie "$t"/b.o -o "$t"/pie
+readelf -rW "$t"/pie | grep -q R_X86_64_RELATIVE || exit 1
+[ "$("$t"/pie)" = "1" ]
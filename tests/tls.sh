@@ -0,0 +1,95 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+cat <<EOF | gcc -o "$t"/a.o -c -xc -
+#include <stdio.h>
+
+_Thread_local int a = 3;
+_Thread_local char b[100];
+extern _Thread_local int c;
+int get(void);
+
+int main(void) {
+    b[99] = 7;
+    c += a;
+    printf("%d %d %d %d\n", a, b[99], c, get());
+    return 0;
+}
+EOF
+# general and local dynamic accesses, relaxed in an executable
+cat <<EOF | gcc -o "$t"/b.o -c -xc -fpic -
+_Thread_local int c = 41;
+static _Thread_local int d = 5;
+
+int get(void) {
+    return c + d;
+}
+EOF
+
+gcc -B. -fno-lto -static "$t"/a.o "$t"/b.o -o "$t"/out
+readelf -lW "$t"/out | grep -q '^  TLS ' || exit 1
+[ "$("$t"/out)" = "3 7 44 49" ] || exit 1
+
+# initial exec accesses to a local variable of a shared object
+cat <<EOF | gcc -o "$t"/c.o -c -xc -fpic -ftls-model=initial-exec -
+static _Thread_local int e[4] = { 1, 2, 3, 4 };
+static _Thread_local int f = 2;
+
+int get(void) {
+    return e[3] * 10 + f;
+}
+EOF
+
+cat <<EOF | gcc -o "$t"/d.o -c -xc -
+#include <stdio.h>
+
+int get(void);
+
+int main(void) {
+    printf("%d\n", get());
+    return 0;
+}
+EOF
+
+gcc -B. -fno-lto -shared "$t"/c.o -o "$t"/libfoo.so
+readelf -rW "$t"/libfoo.so | grep -q R_X86_64_TPOFF64 || exit 1
+gcc -B. -fno-lto "$t"/d.o -o "$t"/shared -L"$t" -lfoo
+[ "$(LD_LIBRARY_PATH="$t" "$t"/shared)" = "42" ] || exit 1
+
+# a general dynamic access to a variable of a shared object, relaxed to
+# initial exec in an executable
+cat <<EOF | gcc -o "$t"/f.o -c -xc -fpic -
+_Thread_local int g = 5;
+EOF
+
+cat <<EOF | gcc -o "$t"/g.o -c -xc -fpic -
+#include <stdio.h>
+
+extern _Thread_local int g;
+
+int main(void) {
+    g *= 3;
+    printf("%d\n", g);
+    return 0;
+}
+EOF
+
+gcc -B. -fno-lto -shared "$t"/f.o -o "$t"/libbar.so
+gcc -B. -fno-lto "$t"/g.o -o "$t"/ie -L"$t" -lbar
+readelf -rW "$t"/ie | grep -q 'R_X86_64_TPOFF64 .* g + 0' || exit 1
+[ "$(LD_LIBRARY_PATH="$t" "$t"/ie)" = "15" ] || exit 1
+
+# a general dynamic access must be followed by the call
+cat <<EOF | gcc -o "$t"/e.o -c -xassembler -
+.globl main
+main:
+    .byte 0x66
+    leaq c@tlsgd(%rip), %rdi
+EOF
+
+! gcc -B. -fno-lto -static "$t"/e.o "$t"/b.o -o "$t"/fail 2> "$t"/log || exit 1
+grep -q 'R_X86_64_TLSGD is not followed by a call to __tls_get_addr' "$t"/log
@@ -0,0 +1,62 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+cat <<EOF2 | gcc -o "$t"/a.o -c -xc -
+#include <stdio.h>
+
+void missing(void);
+extern int maybe __attribute__((weak));
+
+int main() {
+    if (&maybe)
+        missing();
+    printf("%d\n", &maybe == 0);
+    return 0;
+}
+EOF2
+
+! gcc -B. -fno-lto -static "$t"/a.o -o "$t"/fail 2> "$t"/log || exit 1
+grep -qF 'a.o: undefined symbol: missing' "$t"/log || exit 1
+
+# only the weak reference is left, which is 0
+cat <<EOF2 | gcc -o "$t"/b.o -c -xc -
+void missing(void) {}
+EOF2
+
+gcc -B. -fno-lto -static "$t"/a.o "$t"/b.o -o "$t"/out
+[ "$("$t"/out)" = "1" ] || exit 1
+
+# a shared object imports what it doesn't define from the executable
+cat <<EOF2 | gcc -o "$t"/c.o -c -xc -fPIC -
+extern int counter;
+int next(void);
+
+int get(void) {
+    return counter + next();
+}
+EOF2
+
+cat <<EOF2 | gcc -o "$t"/d.o -c -xc -
+#include <stdio.h>
+
+int counter = 40;
+int next(void) { return 2; }
+int get(void);
+
+int main() {
+    printf("%d\n", get());
+    return 0;
+}
+EOF2
+
+gcc -B. -fno-lto -shared "$t"/c.o -o "$t"/libfoo.so
+readelf -W --dyn-syms "$t"/libfoo.so | grep -Eq 'UND counter$' || exit 1
+readelf -rW "$t"/libfoo.so > "$t"/log
+grep -q 'R_X86_64_GLOB_DAT .* counter' "$t"/log || exit 1
+grep -q 'R_X86_64_JUMP_SLOT .* next' "$t"/log || exit 1
+gcc -B. -fno-lto "$t"/d.o -o "$t"/exe -L"$t" -lfoo
+[ "$(LD_LIBRARY_PATH="$t" "$t"/exe)" = "42" ]
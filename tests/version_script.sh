@@ -0,0 +1,52 @@
+#!/bin/bash
+
+test_name=$(basename "$0" .sh)
+t=out/tests/$test_name
+
+mkdir -p "$t"
+
+cat <<EOF | gcc -fPIC -o "$t"/a.o -c -xc -
+int foo(void) { return 3; }
+int bar(void) { return 4; }
+int baz(void) { return 5; }
+EOF
+
+cat <<EOF > "$t"/a.ver
+VER_1 { global: foo; local: *; };
+VER_2 { global: bar; } VER_1;
+EOF
+
+gcc -B. -fno-lto -shared "$t"/a.o -o "$t"/libfoo.so -Wl,--version-script="$t"/a.ver
+readelf -W --dyn-syms -V "$t"/libfoo.so > "$t"/log
+
+grep -Eq 'FUNC +GLOBAL +DEFAULT +[0-9]+ foo@@VER_1$' "$t"/log || exit 1
+grep -Eq 'FUNC +GLOBAL +DEFAULT +[0-9]+ bar@@VER_2$' "$t"/log || exit 1
+! grep -q ' baz' "$t"/log || exit 1
+grep -q 'Index: 2  Cnt: 1  Name: VER_1' "$t"/log || exit 1
+grep -q 'Index: 3  Cnt: 2  Name: VER_2' "$t"/log || exit 1
+
+# the exported symbols are defined in .text
+text=$(readelf -SW "$t"/libfoo.so | sed -n 's/^ *\[ *\([0-9]*\)\] \.text .*/\1/p')
+read -r _ value _ _ _ _ ndx _ < <(grep ' foo@@VER_1$' "$t"/log)
+[ "$ndx" = "$text" ] && [ $((0x$value)) -ne 0 ] || exit 1
+
+readelf -dW "$t"/libfoo.so | grep -q '(VERDEF)' || exit 1
+readelf -dW "$t"/libfoo.so | grep -q '(VERDEFNUM) *3' || exit 1
+readelf -dW "$t"/libfoo.so | grep -q '(VERSYM)' || exit 1
+
+cat <<EOF | gcc -o "$t"/b.o -c -xc -
+#include <stdio.h>
+
+int foo(void);
+int bar(void);
+
+int main(void) {
+    printf("%d %d\n", foo(), bar());
+    return 0;
+}
+EOF
+
+# the executable refers to the versions defined by libfoo.so
+gcc -B. -fno-lto "$t"/b.o -o "$t"/out -L"$t" -lfoo
+readelf -VW "$t"/out | grep -q 'Name: VER_2' || exit 1
+[ "$(LD_LIBRARY_PATH="$t" "$t"/out)" = "3 4" ]